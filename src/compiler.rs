@@ -1,3 +1,437 @@
-pub struct Compiler {
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use frame::{Executor, Frame};
+use inline::Expansion;
+use crate::debug::errors::{error, warning, CompileErr, CompileResult, ErrorInfo, Help};
+use crate::lexer::tokens::{Doc, Ident};
+use crate::parser::{is_resource_char, Datapack, Function, Scoreboard, Statement};
 
+pub mod blocks;
+pub mod calls;
+pub mod expr;
+pub mod frame;
+pub mod inline;
+pub mod macros;
+pub mod text;
+#[cfg(test)]
+mod runner;
+
+pub const PACK_FORMAT:u32 = 10;
+// the directory the datapack is written to
+pub const OUT_DIR:&str = "out";
+pub const LOAD_FUNCTION:&str = "__load";
+// the iterations a loop runs each tick when the datapack doesn't set `loop_limit`
+pub const LOOP_LIMIT:i32 = 1000;
+
+pub struct Compiler<'a> {
+	datapack: &'a Datapack,
+	// every datapack in the compilation, the included ones first
+	packs: &'a [Datapack],
+	namespace: String,
+	files: Vec<OutFile>,
+	scoreboards: Vec<Scoreboard>,
+	// objectives the compiler created for its own holders
+	internal_objectives: Vec<String>,
+	// the functions and inlines called by every function and inline
+	calls: HashMap<String, Vec<String>>,
+	// the inlines that are being expanded, innermost last
+	expansions: Vec<Expansion>,
+	// the iterations a loop can run each tick before it's continued in the next, 0 for no limit
+	loop_limit: i32,
+}
+impl<'a> Compiler<'a> {
+	pub fn new(datapack:&'a Datapack, packs:&'a [Datapack]) -> Self {
+		// nothing is being expanded yet, so the error doesn't need the notes of `fatal`
+		let namespace = resource_name(&datapack.name, "datapack declaration").fatal();
+		let calls = calls::call_graph(namespace.as_str(), datapack);
+		Compiler {
+			datapack,
+			packs,
+			namespace,
+			files: Vec::new(),
+			scoreboards: Vec::new(),
+			internal_objectives: Vec::new(),
+			calls,
+			expansions: Vec::new(),
+			loop_limit: datapack.loop_limit.as_ref()
+				.map_or(LOOP_LIMIT, |limit| limit.value.value),
+		}
+	}
+
+	// errors inside an inline expansion also point at the calls that expanded it
+	fn fatal(&self, err:CompileErr) -> ! {
+		let mut err = err;
+		for expansion in self.expansions.iter().rev() {
+			err = err.note(expansion.call.clone(), format!(
+				"in this expansion of `{}`", expansion.name
+			).as_str());
+		}
+		err.fatal()
+	}
+	// the value of a result, or its error reported with `fatal`
+	fn check<T:Debug>(&self, result:CompileResult<T>) -> T {
+		match result {
+			CompileResult::Ok(value) => value,
+			CompileResult::Err(err) => self.fatal(err),
+		}
+	}
+
+	pub fn compile(mut self) -> Vec<OutFile> {
+		// the datapack being built is the last one, the others are only included in it
+		let built = std::ptr::eq(self.datapack, self.packs.last().unwrap());
+		if built {
+			self.files.push(OutFile {
+				path: "pack.mcmeta".to_owned(),
+				contents: pack_mcmeta(&self.datapack.name.value),
+			});
+		}
+		for scoreboard in &self.datapack.scoreboards {
+			self.add_scoreboard(scoreboard.clone());
+		}
+		for function in &self.datapack.functions {
+			self.compile_function(function);
+		}
+		self.compile_load();
+		if built {
+			self.compile_tags();
+		}
+		self.check_included_entry_points();
+		self.files
+	}
+
+	fn compile_load(&mut self) {
+		let mut commands = Vec::new();
+		for scoreboard in &self.scoreboards {
+			commands.extend(doc_header(&scoreboard.docs));
+			commands.push(format!(
+				"scoreboard objectives add {} {}",
+				scoreboard.name.value, scoreboard.objective.value
+			));
+		}
+		for objective in &self.internal_objectives {
+			commands.push(format!("scoreboard objectives add {} dummy", objective));
+		}
+		let path = self.function_path(LOAD_FUNCTION);
+		self.files.push(OutFile::function(path, commands));
+	}
+
+	fn compile_tags(&mut self) {
+		// every datapack sets up its own scoreboards, the included ones first
+		let mut load:Vec<String> = self.packs.iter()
+			.map(|pack| format!("{}:{}", pack.name.value, LOAD_FUNCTION))
+			.collect();
+		if self.entry_point("main") {
+			load.push(format!("{}:main", self.namespace));
+		}
+		self.files.push(OutFile::tag("data/minecraft/tags/functions/load.json", load));
+
+		if self.entry_point("tick") {
+			let tick = vec![format!("{}:tick", self.namespace)];
+			self.files.push(OutFile::tag("data/minecraft/tags/functions/tick.json", tick));
+		}
+	}
+
+	// `main` and `tick` at the root of the datapack are called by the game,
+	// so they can't take arguments or have guards
+	fn entry_point(&self, name:&str) -> bool {
+		let Some(function) = self.datapack.functions.iter()
+			.find(|function| function.path() == name)
+		else { return false };
+
+		let args = &function.args;
+		if !args.input.is_empty() || !args.output.is_empty() || args.objective.is_some() {
+			self.fatal(error(args.parens.clone(), ErrorInfo {
+				cause: "invalid entry point",
+				pointer: "this function is called by the game, it can't have arguments",
+				context: Some("function declaration"),
+				help: None,
+			}));
+		}
+		if let Some(at) = &function.at {
+			self.fatal(error(at.ident.scope.join(at.loc_scope.clone()), ErrorInfo {
+				cause: "invalid entry point",
+				pointer: "this function is called by the game, it can't be guarded",
+				context: Some("function declaration"),
+				help: None,
+			}));
+		}
+		true
+	}
+
+	// the game only runs `main` and `tick` of the datapack being built,
+	// so the ones of the included datapacks have to be called from ours
+	fn check_included_entry_points(&self) {
+		for include in &self.datapack.includes {
+			let namespace = include.namespace.as_ref().unwrap();
+			let pack = self.pack(namespace);
+			for name in ["main", "tick"] {
+				if !pack.functions.iter().any(|function| function.path() == name) { continue }
+				if self.calls_into(name, namespace, name) { continue }
+				warning(include.path.scope.clone(), ErrorInfo {
+					cause: "uncalled entry point",
+					pointer: format!(
+						"`{}:{}` is never called from `{}`", namespace, name, name
+					).as_str(),
+					context: Some("include"),
+					help: Some(Help {
+						msg: format!("call it in `{}`", name),
+						source: format!("{}:{}();", namespace, name),
+						line: 0,
+					}),
+				}).warn();
+			}
+		}
+	}
+
+	// whether the function `from` ends up calling `function` of the datapack `namespace`
+	fn calls_into(&self, from:&str, namespace:&str, function:&str) -> bool {
+		if !self.datapack.functions.iter().any(|existing| existing.path() == from) { return false }
+		for path in self.reachable(from) {
+			let functions = self.datapack.functions.iter()
+				.filter(|function| function.path() == path)
+				.map(|function| &function.body);
+			let inlines = self.datapack.inlines.iter()
+				.filter(|inline| inline.path() == path)
+				.map(|inline| &inline.body);
+			let mut calls = Vec::new();
+			for body in functions.chain(inlines) {
+				calls::collect_calls(body, &mut calls);
+			}
+			let called = calls.iter().any(|call| {
+				call.path.namespace.as_deref() == Some(namespace) && call.path.path == function
+			});
+			if called { return true }
+		}
+		false
+	}
+
+	fn pack(&self, namespace:&str) -> &'a Datapack {
+		self.packs.iter().find(|pack| pack.name.value == namespace).unwrap()
+	}
+
+	fn add_scoreboard(&mut self, scoreboard:Scoreboard) {
+		let existing = self.scoreboards.iter()
+			.find(|existing| existing.name.value == scoreboard.name.value);
+		match existing {
+			None => self.scoreboards.push(scoreboard),
+			// the same objective declared in multiple functions is only created once
+			Some(existing) if existing.objective.value == scoreboard.objective.value => {},
+			Some(_) => self.fatal(error(scoreboard.objective.scope, ErrorInfo {
+				cause: "conflicting scoreboard declaration",
+				pointer: "a scoreboard with this name but a different criteria has already been declared",
+				context: Some("scoreboard declaration"),
+				help: None,
+			}))
+		}
+	}
+
+	fn function_path(&self, name:&str) -> String {
+		format!("data/{}/functions/{}.mcfunction", self.namespace, name)
+	}
+
+	fn compile_function(&mut self, function:&Function) {
+		let name = self.check(resource_name(&function.name, "function declaration"));
+		if name.starts_with("__") {
+			self.fatal(error(function.name.scope.clone(), ErrorInfo {
+				cause: "reserved function name",
+				pointer: "names starting with `__` are used by generated functions",
+				context: Some("function declaration"),
+				help: None,
+			}));
+		}
+		let path = self.function_path(function.path().as_str());
+		if self.files.iter().any(|file| file.path == path) {
+			self.fatal(error(function.name.scope.clone(), ErrorInfo {
+				cause: "duplicate function",
+				pointer: "a function with this name has already been defined in this module",
+				context: Some("function declaration"),
+				help: None,
+			}));
+		}
+
+		let (inputs, outputs) = self.check(calls::params(function));
+		let params = inputs.into_iter().chain(outputs).collect();
+		let entry_point = function.module.is_empty() && (name == "main" || name == "tick");
+		let executor = match &function.at {
+			_ if entry_point => Executor::Server,
+			Some(at) => Executor::Known(at.loc.clone()),
+			None => Executor::Unknown,
+		};
+		let mut frame = Frame::new(self.namespace.as_str(), function.module.as_str(), name.as_str(), params, executor);
+		let mut commands = doc_header(&function.docs);
+		commands.extend(self.compile_block(&mut frame, &function.body));
+		if frame.used {
+			self.internal_objectives.push(frame.objective);
+		}
+		self.files.push(OutFile::function(path, commands));
+	}
+
+	fn compile_block(&mut self, frame:&mut Frame, statements:&[Statement]) -> Vec<String> {
+		let mut commands = Vec::new();
+		let followed = frame.followed;
+		frame.enter_scope();
+		for (i, statement) in statements.iter().enumerate() {
+			let rest = &statements[i+1..];
+			frame.followed = followed || !rest.is_empty();
+			match statement {
+				Statement::Unsafe(unsafe_block) => {
+					for command in &unsafe_block.commands {
+						commands.push(self.unsafe_command(command));
+					}
+				}
+				Statement::Scoreboard(scoreboard) => {
+					self.add_scoreboard(scoreboard.clone());
+				}
+				Statement::Call(call) => {
+					self.compile_call(frame, call, None, &mut commands);
+				}
+				Statement::Pipe(pipe) => {
+					self.compile_pipe(frame, pipe, &mut commands);
+				}
+				Statement::Let(local) => {
+					self.compile_let(frame, local, &mut commands);
+				}
+				Statement::Assign(assign) => {
+					self.compile_assign(frame, assign, &mut commands);
+				}
+				Statement::At(at) => {
+					self.compile_at(frame, at, &mut commands);
+				}
+				Statement::If(if_statement) => {
+					self.compile_if(frame, if_statement, &mut commands);
+				}
+				// a limited loop compiles the rest of the block into its exit path
+				Statement::While(while_loop) => {
+					frame.followed = followed;
+					self.compile_while(frame, while_loop, rest, &mut commands);
+					if self.loop_limit != 0 { break }
+				}
+				Statement::For(for_loop) => {
+					frame.followed = followed;
+					self.compile_for(frame, for_loop, rest, &mut commands);
+					if self.loop_limit != 0 { break }
+				}
+				Statement::Macro(call) => {
+					self.compile_macro(frame, call, &mut commands);
+				}
+			}
+		}
+		frame.followed = followed;
+		frame.exit_scope();
+		commands
+	}
+}
+
+pub struct OutFile {
+	pub path: String,
+	pub contents: String,
+}
+impl OutFile {
+	pub fn function(path:String, commands:Vec<String>) -> Self {
+		let mut contents = String::new();
+		for command in commands {
+			contents.push_str(command.as_str());
+			contents.push('\n');
+		}
+		OutFile { path, contents }
+	}
+	pub fn tag(path:&str, values:Vec<String>) -> Self {
+		let values:Vec<String> = values.iter()
+			.map(|value| format!("\t\t\"{}\"", value))
+			.collect();
+		let contents = format!(
+			"{{\n\t\"values\": [\n{}\n\t]\n}}\n",
+			values.join(",\n")
+		);
+		OutFile { path: path.to_owned(), contents }
+	}
+}
+
+// the output directory is only cleared if it holds a datapack, so nothing else is ever deleted
+pub fn write_files(root:&Path, files:&[OutFile]) {
+	if root.join("pack.mcmeta").is_file() {
+		fs::remove_dir_all(root).unwrap_or_else(|_| panic!(
+			"failed to clear output directory {}", root.display()
+		));
+	} else if root.exists() && !fs::read_dir(root).is_ok_and(|mut entries| entries.next().is_none()) {
+		panic!("output directory {} isn't empty and doesn't hold a datapack", root.display());
+	}
+	for file in files {
+		let path = root.join(&file.path);
+		fs::create_dir_all(path.parent().unwrap()).unwrap_or_else(|_| panic!(
+			"failed to create directory for {}", path.display()
+		));
+		fs::write(&path, &file.contents).unwrap_or_else(|_| panic!(
+			"failed to write file {}", path.display()
+		));
+	}
+}
+
+fn pack_mcmeta(description:&str) -> String {
+	format!(
+		"{{\n\t\"pack\": {{\n\t\t\"pack_format\": {},\n\t\t\"description\": {}\n\t}}\n}}\n",
+		PACK_FORMAT, text::json_string(description)
+	)
+}
+
+// doc comments are kept as comments at the top of the output
+fn doc_header(docs:&[Doc]) -> Vec<String> {
+	docs.iter().map(|doc| match doc.value.as_str() {
+		"" => "#".to_owned(),
+		line => format!("# {}", line),
+	}).collect()
+}
+
+// namespaces and function names end up as file names in the datapack,
+// so they must only use the characters Minecraft allows there
+fn resource_name(ident:&Ident, context:&str) -> CompileResult<String> {
+	if !ident.value.chars().all(is_resource_char) {
+		return CompileResult::Err(error(ident.scope.clone(), ErrorInfo {
+			cause: "invalid resource name",
+			pointer: "only lowercase letters, digits, `_`, `-` and `.` are allowed here",
+			context: Some(context),
+			help: None,
+		}));
+	}
+	CompileResult::Ok(ident.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a directory of its own for every test, since they run in parallel
+	fn out_dir(name:&str) -> std::path::PathBuf {
+		let dir = std::env::temp_dir().join(format!("pack-lang-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	#[test]
+	fn description_is_escaped() {
+		assert!(pack_mcmeta("a \"b\" \\ c").contains("\"description\": \"a \\\"b\\\" \\\\ c\""));
+	}
+
+	#[test]
+	fn only_datapacks_are_cleared() {
+		let files = [OutFile { path: "pack.mcmeta".to_owned(), contents: pack_mcmeta("test") }];
+		let dir = out_dir("ours");
+		fs::create_dir_all(dir.join("data")).unwrap();
+		fs::write(dir.join("pack.mcmeta"), "{}").unwrap();
+		fs::write(dir.join("data/old.txt"), "").unwrap();
+		write_files(&dir, &files);
+		assert!(!dir.join("data").exists());
+		assert!(dir.join("pack.mcmeta").is_file());
+		fs::remove_dir_all(&dir).unwrap();
+
+		let dir = out_dir("other");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("notes.txt"), "keep").unwrap();
+		assert!(std::panic::catch_unwind(|| write_files(&dir, &files)).is_err());
+		assert_eq!(fs::read_to_string(dir.join("notes.txt")).unwrap(), "keep");
+		assert!(!dir.join("pack.mcmeta").exists());
+		fs::remove_dir_all(&dir).unwrap();
+	}
 }
@@ -0,0 +1,326 @@
+use crate::compiler::{Compiler, OutFile};
+use crate::compiler::expr::free_all;
+use crate::compiler::frame::{Executor, Frame, Score, Value};
+use crate::debug::errors::{error, warning, ErrorInfo, Help};
+use crate::lexer::tokens::Ident;
+use crate::parser::{AtBlock, For, If, Statement, While};
+
+impl<'a> Compiler<'a> {
+	// compiles a block into its own function, and returns the name to call it with
+	pub(super) fn compile_sub_function(&mut self, frame:&mut Frame, statements:&[Statement]) -> String {
+		let name = frame.block_function();
+		let commands = self.compile_block(frame, statements);
+		self.add_function(name.as_str(), commands)
+	}
+
+	fn add_function(&mut self, name:&str, commands:Vec<String>) -> String {
+		let path = self.function_path(name);
+		self.files.push(OutFile::function(path, commands));
+		format!("{}:{}", self.namespace, name)
+	}
+
+	pub(super) fn compile_at(&mut self, frame:&mut Frame, at:&AtBlock, commands:&mut Vec<String>) {
+		let Value::Target(selector) = self.arg_value(frame, &at.target, "at block") else {
+			self.fatal(error(at.target.scope(), ErrorInfo {
+				cause: "invalid target",
+				pointer: "expected a target selector",
+				context: Some("at block"),
+				help: None,
+			}))
+		};
+		// the block runs as every matched entity, so `@s` changes while it's compiled
+		let executor = frame.executor.of_selector(&selector);
+		let outer = std::mem::replace(&mut frame.executor, executor);
+		let function = self.compile_sub_function(frame, &at.body);
+		frame.executor = outer;
+
+		commands.push(format!("execute as {} at @s run function {}", selector, function));
+	}
+
+	pub(super) fn compile_if(&mut self, frame:&mut Frame, if_statement:&If, commands:&mut Vec<String>) {
+		let check = self.compile_condition(frame, &if_statement.condition, commands);
+		let Some(otherwise) = &if_statement.otherwise else {
+			let then = self.compile_sub_function(frame, &if_statement.body);
+			commands.push(format!("execute {} run function {}", check, then));
+			return;
+		};
+		// the first branch can change the compared scores, so the result is kept for the second one
+		let flag = frame.temp();
+		commands.push(format!("execute store success score {} {}", flag, check));
+		let then = self.compile_sub_function(frame, &if_statement.body);
+		let otherwise = self.compile_sub_function(frame, &otherwise.body);
+		commands.push(format!("execute if score {} matches 1 run function {}", flag, then));
+		commands.push(format!("execute if score {} matches 0 run function {}", flag, otherwise));
+		frame.free(&flag);
+	}
+
+	pub(super) fn compile_while(&mut self, frame:&mut Frame, while_loop:&While, rest:&[Statement], commands:&mut Vec<String>) {
+		let name = frame.block_function();
+		let limit = self.start_loop(frame, &while_loop.ident, rest, commands);
+		let check = self.compile_condition(frame, &while_loop.condition, commands);
+		self.run_loop(name.as_str(), limit.as_ref(), check.as_str(), commands);
+
+		let outer = self.enter_loop(frame);
+		let mut body = self.compile_block(frame, &while_loop.body);
+		let check = self.compile_condition(frame, &while_loop.condition, &mut body);
+		self.repeat(frame, name.as_str(), limit.as_ref(), check, &mut body);
+		self.exit_loop(frame, outer, limit, rest);
+		self.add_function(name.as_str(), body);
+	}
+
+	pub(super) fn compile_for(&mut self, frame:&mut Frame, for_loop:&For, rest:&[Statement], commands:&mut Vec<String>) {
+		let name = frame.block_function();
+		// the variable is a local of the loop, declared once the range is computed
+		let var = self.new_local(frame, &for_loop.var);
+		let mut temps = Vec::new();
+		self.eval_into(frame, &for_loop.start, &var, "for loop range", &mut temps, commands);
+		// the end is only evaluated once, so it's kept for the later iterations
+		let check = match self.constant(frame, &for_loop.end, "for loop range") {
+			Some(int) if int != i32::MIN => format!("if score {} matches ..{}", var, int-1),
+			_ => {
+				let holder = frame.keep(format!("#end{}", frame.blocks).as_str());
+				self.eval_into(frame, &for_loop.end, &holder, "for loop range", &mut temps, commands);
+				format!("if score {} < {}", var, holder)
+			}
+		};
+		free_all(frame, &temps);
+		frame.enter_scope();
+		frame.declare(&for_loop.var);
+		let limit = self.start_loop(frame, &for_loop.ident, rest, commands);
+		self.run_loop(name.as_str(), limit.as_ref(), check.as_str(), commands);
+
+		let outer = self.enter_loop(frame);
+		let mut body = self.compile_block(frame, &for_loop.body);
+		body.push(format!("scoreboard players add {} 1", var));
+		self.repeat(frame, name.as_str(), limit.as_ref(), check, &mut body);
+		frame.exit_scope();
+		self.exit_loop(frame, outer, limit, rest);
+		self.add_function(name.as_str(), body);
+	}
+
+	// resets the iteration counter of a new loop, if loops are limited.
+	// the code after a limited loop has to wait for it, so the rest of the block is run when it ends
+	fn start_loop(&mut self, frame:&mut Frame, keyword:&Ident, rest:&[Statement], commands:&mut Vec<String>) -> Option<Limit> {
+		if self.loop_limit == 0 { return None }
+		self.check_limited_loop(frame, keyword);
+		let counter = frame.keep(format!("#loop{}", frame.blocks).as_str());
+		commands.push(format!("scoreboard players set {} 0", counter));
+		let after = if rest.is_empty() { None } else {
+			let result = frame.keep(format!("#check{}", frame.blocks).as_str());
+			Some((result, frame.block_function()))
+		};
+		Some(Limit { counter, after })
+	}
+
+	// the rest of the function only waits for a limited loop if it's in the same block,
+	// and a function that can call itself could start the loop again while it's paused
+	fn check_limited_loop(&self, frame:&Frame, keyword:&Ident) {
+		if frame.followed {
+			warning(keyword.scope.clone(), ErrorInfo {
+				cause: "unfinished loop",
+				pointer: "when this loop is continued in the next tick, the code after the block it's in runs before it ends",
+				context: Some("loop"),
+				help: Some(Help {
+					msg: "move the code after the block into it, or remove the limit of loops".to_owned(),
+					source: "loop_limit 0;".to_owned(),
+					line: 0,
+				}),
+			}).warn();
+		}
+		let recursive = self.calls.get(&frame.function)
+			.is_some_and(|calls| calls.iter().any(|callee| self.reachable(callee).contains(&frame.function)));
+		if recursive {
+			warning(keyword.scope.clone(), ErrorInfo {
+				cause: "unfinished loop",
+				pointer: "this function can call itself, which runs this loop again while it's continued in the next tick",
+				context: Some("loop"),
+				help: None,
+			}).warn();
+		}
+	}
+
+	// calls the loop function if `check` holds, and otherwise the rest of the block
+	fn run_loop(&self, name:&str, limit:Option<&Limit>, check:&str, commands:&mut Vec<String>) {
+		let function = format!("{}:{}", self.namespace, name);
+		let Some((result, after)) = limit.and_then(|limit| limit.after.as_ref()) else {
+			commands.push(format!("execute {} run function {}", check, function));
+			return;
+		};
+		// the rest runs first, the loop changes the result when it ends
+		commands.push(format!("execute store success score {} {}", result, check));
+		commands.push(format!("execute if score {} matches 0 run function {}:{}", result, self.namespace, after));
+		commands.push(format!("execute if score {} matches 1 run function {}", result, function));
+	}
+
+	// a loop that is continued in the next tick is run by the server, so `@s` is only known
+	// while it runs if it's always the server. the body is always followed by the next iteration.
+	// returns the executor outside of the loop, and whether more of the function runs after it
+	fn enter_loop(&self, frame:&mut Frame) -> (Executor, bool) {
+		let executor = match &frame.executor {
+			Executor::Known(_) if self.loop_limit != 0 => Executor::Unknown,
+			executor => executor.clone(),
+		};
+		let followed = std::mem::replace(&mut frame.followed, true);
+		(std::mem::replace(&mut frame.executor, executor), followed)
+	}
+
+	// compiles the rest of the block into the function the loop runs when it ends,
+	// which is run by the server too if the loop was continued in the next tick
+	fn exit_loop(&mut self, frame:&mut Frame, outer:(Executor, bool), limit:Option<Limit>, rest:&[Statement]) {
+		frame.followed = outer.1;
+		if let Some(Limit { after: Some((_, after)), .. }) = limit {
+			let commands = self.compile_block(frame, rest);
+			self.add_function(after.as_str(), commands);
+		}
+		frame.executor = outer.0;
+	}
+
+	// runs the loop function again while `check` holds,
+	// in the next tick once it has run `loop_limit` times in this one
+	fn repeat(&mut self, frame:&mut Frame, name:&str, limit:Option<&Limit>, check:String, commands:&mut Vec<String>) {
+		let function = format!("{}:{}", self.namespace, name);
+		let Some(limit) = limit else {
+			commands.push(format!("execute {} run function {}", check, function));
+			return;
+		};
+		let counter = &limit.counter;
+		commands.push(format!("scoreboard players add {} 1", counter));
+		let check = match &limit.after {
+			None => check,
+			Some((result, after)) => {
+				commands.push(format!("execute store success score {} {}", result, check));
+				commands.push(format!("execute if score {} matches 0 run function {}:{}", result, self.namespace, after));
+				format!("if score {} matches 1", result)
+			}
+		};
+		commands.push(format!(
+			"execute if score {} matches ..{} {} run function {}",
+			counter, self.loop_limit-1, check, function
+		));
+		let pause = frame.block_function();
+		let pause = self.add_function(pause.as_str(), vec![
+			format!("scoreboard players set {} 0", counter),
+			format!("schedule function {} 1t append", function),
+		]);
+		commands.push(format!(
+			"execute if score {} matches {}.. {} run function {}",
+			counter, self.loop_limit, check, pause
+		));
+	}
+}
+
+// the iteration counter of a limited loop, and where the result of its check is kept
+// with the function that runs the rest of the block, if there's code after the loop
+struct Limit {
+	counter: Score,
+	after: Option<(Score, String)>,
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::compiler::runner::{compile, Runner};
+
+	const COUNT:&str = "datapack test;
+		loop_limit 3;
+		function count(n -> i : math) {
+			i = 0;
+			while i < n { i += 1; }
+			unsafe { say done }
+		}";
+
+	// calls `function` with `n`, and runs the ticks until nothing is scheduled
+	fn run(runner:&mut Runner, function:&str, n:i32) -> usize {
+		runner.set("n", "math", n);
+		runner.call(function);
+		let mut ticks = 0;
+		while runner.tick() {
+			ticks += 1;
+		}
+		ticks
+	}
+
+	#[test]
+	fn code_after_a_limited_loop_waits_for_it() {
+		let mut runner = Runner::new(COUNT);
+		runner.set("n", "math", 7);
+		runner.call("test:count");
+		assert_eq!(runner.get("i", "math"), 3);
+		assert!(runner.said.is_empty());
+		runner.tick();
+		assert_eq!(runner.get("i", "math"), 6);
+		assert!(runner.said.is_empty());
+		runner.tick();
+		assert_eq!(runner.get("i", "math"), 7);
+		assert_eq!(runner.said, vec!["done"]);
+		assert!(!runner.is_scheduled());
+	}
+
+	#[test]
+	fn code_after_a_loop_runs_once() {
+		// the check fails on entry, in the first tick, and right after a pause
+		for (n, ticks) in [(0, 0), (-4, 0), (2, 0), (3, 0), (4, 1), (6, 1), (9, 2)] {
+			let mut runner = Runner::new(COUNT);
+			assert_eq!(run(&mut runner, "test:count", n), ticks, "ticks for {}", n);
+			assert_eq!(runner.get("i", "math"), n.max(0));
+			assert_eq!(runner.said, vec!["done"], "output for {}", n);
+		}
+	}
+
+	#[test]
+	fn code_after_a_for_loop_runs_once() {
+		let source = "datapack test;
+			loop_limit 2;
+			function sum(n -> total : math) {
+				total = 0;
+				for x in 0..n { total += x; }
+				unsafe { say done }
+			}";
+		for (n, ticks) in [(0, 0), (1, 0), (2, 0), (3, 1), (4, 1), (5, 2)] {
+			let mut runner = Runner::new(source);
+			assert_eq!(run(&mut runner, "test:sum", n), ticks, "ticks for {}", n);
+			assert_eq!(runner.get("total", "math"), (0..n).sum::<i32>());
+			assert_eq!(runner.said, vec!["done"], "output for {}", n);
+		}
+	}
+
+	#[test]
+	fn nested_loops() {
+		let mut runner = Runner::new("datapack test;
+			function grid(n -> total, rows : math) {
+				total = 0;
+				rows = 0;
+				for x in 0..n {
+					for y in 0..n { total += 1; }
+					rows += 1;
+				}
+				unsafe { say done }
+			}");
+		for n in [0, 1, 4] {
+			runner.said.clear();
+			assert_eq!(run(&mut runner, "test:grid", n), 0);
+			assert_eq!(runner.get("total", "math"), n * n);
+			assert_eq!(runner.get("rows", "math"), n);
+			assert_eq!(runner.said, vec!["done"]);
+		}
+	}
+
+	#[test]
+	fn unlimited_loops_finish_in_one_tick() {
+		let source = "datapack test;
+			loop_limit 0;
+			function count(n -> i : math) {
+				i = 0;
+				while i < n { i += 1; }
+				unsafe { say done }
+			}";
+		let files = compile(source);
+		for file in &files {
+			assert!(!file.contents.contains("schedule"), "{} is scheduled", file.path);
+			assert!(!file.contents.contains("#loop"), "{} counts iterations", file.path);
+		}
+		let mut runner = Runner::new(source);
+		assert_eq!(run(&mut runner, "test:count", 50), 0);
+		assert_eq!(runner.get("i", "math"), 50);
+		assert_eq!(runner.said, vec!["done"]);
+	}
+}
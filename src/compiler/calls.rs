@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use crate::compiler::Compiler;
+use crate::compiler::expr::{free_all, keep_for};
+use crate::compiler::frame::{Frame, Score, Value};
+use crate::debug::errors::{error, CompileResult, ErrorInfo, Help};
+use crate::lexer::tokens::{Ident, SrcScope, Token};
+use crate::parser::{module_path, Arg, AtLoc, Call, Datapack, Function, Inline, Pipe, ResLoc, Statement};
+
+pub const STACK_STORAGE:&str = "__stack";
+
+pub enum Callee<'a> {
+	Function(&'a Function),
+	Inline(&'a Inline),
+	// a function of an included datapack, with the name of the datapack
+	Included(&'a Function, String),
+	// a function of another datapack, called by its resource location
+	External(String),
+}
+impl Callee<'_> {
+	pub fn path(&self) -> String {
+		match self {
+			Self::Function(function) => function.path(),
+			Self::Inline(inline) => inline.path(),
+			Self::Included(function, namespace) => format!("{}:{}", namespace, function.path()),
+			Self::External(function) => function.clone(),
+		}
+	}
+}
+
+impl<'a> Compiler<'a> {
+	pub(super) fn compile_call(
+		&mut self,
+		frame:&mut Frame,
+		call:&Call,
+		pipe:Option<&Pipe>,
+		commands:&mut Vec<String>,
+	) {
+		let used = pipe.map(|pipe| (pipe.name.scope.clone(), "pipe declaration"));
+		let output = self.compile_call_output(frame, call, used, commands);
+		if let (Some(pipe), Some(output)) = (pipe, output) {
+			let holder = frame.bind_pipe(&pipe.name);
+			commands.push(format!("scoreboard players operation {} = {}", holder, output));
+		}
+	}
+
+	// compiles a call and returns the score its output can be read from right after it,
+	// `used` is where the output is used, if it is
+	pub(super) fn compile_call_output(
+		&mut self,
+		frame:&mut Frame,
+		call:&Call,
+		used:Option<(SrcScope, &str)>,
+		commands:&mut Vec<String>,
+	) -> Option<Score> {
+		let (function, namespace) = match self.find_callee(frame, call) {
+			Callee::Function(function) => (function, self.namespace.clone()),
+			Callee::Included(function, namespace) => (function, namespace),
+			Callee::Inline(inline) => {
+				if let Some((scope, context)) = used {
+					self.fatal(error(scope, ErrorInfo {
+						cause: "invalid use of an inline",
+						pointer: "inlines don't return values, only function calls do",
+						context: Some(context),
+						help: None,
+					}));
+				}
+				self.compile_inline_call(frame, call, inline, commands);
+				return None;
+			}
+			Callee::External(function) => {
+				self.compile_external_call(call, function, used, commands);
+				return None;
+			}
+		};
+
+		let (inputs, outputs) = self.check(params(function));
+		if call.args.len() != inputs.len() {
+			self.fatal(error(call.group.scope.clone(), ErrorInfo {
+				cause: "wrong number of arguments",
+				pointer: format!(
+					"this function takes {} arguments, but {} were given",
+					inputs.len(), call.args.len()
+				).as_str(),
+				context: Some("function call"),
+				help: None,
+			}));
+		}
+		// a call in a later argument could change the scores read by the ones before it
+		let mut temps = Vec::new();
+		let mut args:Vec<Value> = Vec::new();
+		for (i, arg) in call.args.iter().enumerate() {
+			let mut value = self.eval(frame, arg, "function call", &mut temps, commands);
+			for later in &call.args[i+1..] {
+				value = keep_for(frame, value, later, &mut temps, commands);
+			}
+			args.push(value);
+		}
+		let output = used.map(|(scope, context)| self.call_output(scope, context, &outputs));
+
+		// scores of the caller that the callee could overwrite
+		let saved = self.saved_scores(frame, function, namespace.as_str());
+		self.push_stack(&saved, commands);
+
+		pass_args(frame, &inputs, args, commands);
+		free_all(frame, &temps);
+		let command = format!("function {}:{}", namespace, function.path());
+		commands.push(self.guard(frame, call, function, command));
+
+		let Some(output) = output else {
+			self.pop_stack(&saved, commands);
+			return None;
+		};
+		if saved.is_empty() {
+			return Some(output);
+		}
+		// the output could be one of the restored scores, so it's moved out of the way first
+		let ret = frame.holder("#ret");
+		commands.push(format!("scoreboard players operation {} = {}", ret, output));
+		self.pop_stack(&saved, commands);
+		Some(ret)
+	}
+
+	pub(super) fn find_callee(&self, frame:&Frame, call:&Call) -> Callee<'a> {
+		if call.path.namespace.as_ref().is_some_and(|namespace| *namespace != self.namespace) {
+			self.check(call.path.check_chars("function call"));
+		}
+		let module = self.caller_module(frame);
+		match find_callee(self.datapack, self.namespace.as_str(), module, call) {
+			Some(Callee::External(path)) => return self.included_callee(call).unwrap_or(Callee::External(path)),
+			Some(callee) => return callee,
+			None => {},
+		}
+		self.fatal(error(call.path.scope.clone(), ErrorInfo {
+			cause: "unknown function",
+			pointer: "no function or inline with this path exists",
+			context: Some("function call"),
+			help: None,
+		}))
+	}
+
+	// the function a call refers to if it's to a datapack this one includes
+	fn included_callee(&self, call:&Call) -> Option<Callee<'a>> {
+		let namespace = call.path.namespace.as_ref()?;
+		let include = self.datapack.includes.iter()
+			.find(|include| include.namespace.as_ref() == Some(namespace))?;
+		let pack = self.pack(namespace);
+		if let Some(function) = pack.functions.iter().find(|function| function.path() == call.path.path) {
+			return Some(Callee::Included(function, namespace.clone()));
+		}
+		let pointer = if pack.inlines.iter().any(|inline| inline.path() == call.path.path) {
+			"inlines of included datapacks can't be expanded"
+		} else {
+			"no function with this path exists in the included datapack"
+		};
+		self.fatal(error(call.path.scope.clone(), ErrorInfo {
+			cause: "unknown function",
+			pointer,
+			context: Some("function call"),
+			help: None,
+		}).note(include.path.scope.clone(), "included here"))
+	}
+
+	// the module calls are resolved from, which is the one of the inline being expanded if there is one
+	fn caller_module<'f>(&'f self, frame:&'f Frame) -> &'f str {
+		match self.expansions.last() {
+			Some(expansion) => expansion.module.as_str(),
+			None => frame.module.as_str(),
+		}
+	}
+
+	// `context` is what the argument is written in, which errors are reported with
+	pub(super) fn arg_value(&self, frame:&Frame, arg:&Arg, context:&str) -> Value {
+		match arg.tokens.as_slice() {
+			[Token::Int(int)] => Value::Int(int.value),
+			[number] if number.is_number() => self.fatal(error(number.scope(), ErrorInfo {
+				cause: "invalid number",
+				pointer: "scores can only hold ints, this number is only valid in NBT",
+				context: Some(context),
+				help: None,
+			})),
+			[Token::Sep(sep), Token::Ident(name)] if sep.value == '|' => {
+				match frame.pipe(name) {
+					Some(score) => Value::Score(score),
+					None => self.fatal(error(name.scope.clone(), ErrorInfo {
+						cause: "unknown pipe",
+						pointer: "no pipe with this name has been declared before",
+						context: Some(context),
+						help: None,
+					})),
+				}
+			}
+			[Token::Ident(name)] if name.value.starts_with('$') => {
+				self.inline_arg(&name.value[1..], name.scope.clone())
+			}
+			[Token::Ident(name)] => match frame.local(name.value.as_str()) {
+				Some(local) => Value::Score(local),
+				None => Value::Name(name.value.clone()),
+			}
+			[Token::Ident(holder), Token::Ident(objective)] => {
+				Value::Score(Score::new(holder.value.as_str(), objective.value.as_str()))
+			}
+			[Token::Selector(selector)] => Value::Target(selector.clone()),
+			tokens if ResLoc::glued(tokens) => {
+				let loc = self.check(ResLoc::parse(tokens, context));
+				self.check(loc.check_chars(context));
+				Value::Resource(loc.to_string())
+			}
+			// a selector followed by an objective
+			[Token::Selector(selector), Token::Ident(objective)] => {
+				Value::Score(Score::new(selector.to_string().as_str(), objective.value.as_str()))
+			}
+			_ => self.fatal(error(arg.scope(), ErrorInfo {
+				cause: "invalid argument",
+				pointer: "expected an integer, a pipe, a score or a target",
+				context: Some(context),
+				help: None,
+			}))
+		}
+	}
+
+	// nothing is known about the functions of other datapacks, so they're called without arguments or outputs
+	fn compile_external_call(&self, call:&Call, function:String, used:Option<(SrcScope, &str)>, commands:&mut Vec<String>) {
+		if let Some((scope, context)) = used {
+			self.fatal(error(scope, ErrorInfo {
+				cause: "missing return value",
+				pointer: "functions of other datapacks don't return values",
+				context: Some(context),
+				help: None,
+			}).note(call.path.scope.clone(), "this function is in another datapack"));
+		}
+		if let Some(arg) = call.args.first() {
+			self.fatal(error(arg.scope(), ErrorInfo {
+				cause: "wrong number of arguments",
+				pointer: "functions of other datapacks can't be given arguments",
+				context: Some("function call"),
+				help: None,
+			}));
+		}
+		commands.push(format!("function {}", function));
+	}
+
+	// makes sure a function with an `at` guard only runs where it's allowed to
+	fn guard(&self, frame:&Frame, call:&Call, function:&Function, command:String) -> String {
+		let Some(at) = &function.at else { return command };
+		match frame.executor.satisfies(&at.loc) {
+			Some(true) => command,
+			Some(false) => self.fatal(error(call.path.scope.clone(), ErrorInfo {
+				cause: "invalid call location",
+				pointer: format!(
+					"`{}` must run at {}, but `{}` is run by {}",
+					function.path(), at.loc.describe(),
+					frame.function, frame.executor.describe()
+				).as_str(),
+				context: Some("function call"),
+				help: None,
+			}).note(at.ident.scope.join(at.loc_scope.clone()), "guard declared here")),
+			None => match at.loc {
+				AtLoc::Player | AtLoc::Players => {
+					format!("execute if entity @s[type=player] run {}", command)
+				}
+				_ => format!("execute if entity @s run {}", command),
+			}
+		}
+	}
+
+	fn call_output(&self, scope:SrcScope, context:&str, outputs:&[Score]) -> Score {
+		match outputs {
+			[output] => output.clone(),
+			[] => self.fatal(error(scope, ErrorInfo {
+				cause: "missing return value",
+				pointer: "this function doesn't return a value",
+				context: Some(context),
+				help: None,
+			})),
+			_ => self.fatal(error(scope, ErrorInfo {
+				cause: "too many return values",
+				pointer: "only one value can be used here, but this function returns multiple",
+				context: Some(context),
+				help: None,
+			})),
+		}
+	}
+
+	fn saved_scores(&self, frame:&Frame, callee:&Function, namespace:&str) -> Vec<Score> {
+		let called:Vec<&Function> = if namespace == self.namespace {
+			let reachable = self.reachable(callee.path().as_str());
+			// a recursive call overwrites everything
+			if reachable.contains(&frame.function) {
+				return frame.state();
+			}
+			self.datapack.functions.iter()
+				.filter(|function| reachable.contains(&function.path()))
+				.collect()
+		} else {
+			// included datapacks can't call back into this one, but any of their functions could run
+			self.packs.iter()
+				.filter(|pack| !std::ptr::eq(*pack, self.datapack))
+				.flat_map(|pack| &pack.functions)
+				.collect()
+		};
+		// only the params the caller shares with a function that could run
+		let mut saved:Vec<Score> = Vec::new();
+		for function in called {
+			let (inputs, outputs) = self.check(params(function));
+			for score in inputs.into_iter().chain(outputs) {
+				if frame.params.contains(&score) && !saved.contains(&score) {
+					saved.push(score);
+				}
+			}
+		}
+		saved
+	}
+
+	// every function and inline `from` can end up calling, including itself
+	pub(super) fn reachable(&self, from:&str) -> Vec<String> {
+		let mut reachable = vec![from.to_owned()];
+		let mut i = 0;
+		while i < reachable.len() {
+			if let Some(calls) = self.calls.get(&reachable[i]) {
+				for name in calls {
+					if !reachable.contains(name) {
+						reachable.push(name.clone());
+					}
+				}
+			}
+			i += 1;
+		}
+		reachable
+	}
+
+	fn push_stack(&self, scores:&[Score], commands:&mut Vec<String>) {
+		if scores.is_empty() { return }
+		commands.push(format!(
+			"data modify storage {}:{} frames append value {{}}",
+			self.namespace, STACK_STORAGE
+		));
+		for (i, score) in scores.iter().enumerate() {
+			commands.push(format!(
+				"execute store result storage {}:{} frames[-1].s{} int 1 run scoreboard players get {}",
+				self.namespace, STACK_STORAGE, i, score
+			));
+		}
+	}
+	fn pop_stack(&self, scores:&[Score], commands:&mut Vec<String>) {
+		if scores.is_empty() { return }
+		for (i, score) in scores.iter().enumerate() {
+			commands.push(format!(
+				"execute store result score {} run data get storage {}:{} frames[-1].s{}",
+				score, self.namespace, STACK_STORAGE, i
+			));
+		}
+		commands.push(format!(
+			"data remove storage {}:{} frames[-1]",
+			self.namespace, STACK_STORAGE
+		));
+	}
+}
+
+fn pass_args(frame:&mut Frame, inputs:&[Score], args:Vec<Value>, commands:&mut Vec<String>) {
+	// an argument that is also an earlier input of the callee would be overwritten before it's read,
+	// so then every score argument is copied to a temporary first
+	let conflict = args.iter().enumerate().any(|(i, arg)| match arg {
+		Value::Score(score) => inputs[..i].contains(score),
+		_ => false,
+	});
+	let args:Vec<Value> = if conflict {
+		args.into_iter().enumerate().map(|(i, arg)| match arg {
+			Value::Score(score) => {
+				let temp = frame.holder(format!("#arg{}", i).as_str());
+				commands.push(format!("scoreboard players operation {} = {}", temp, score));
+				Value::Score(temp)
+			}
+			arg => arg,
+		}).collect()
+	} else { args };
+
+	for (input, arg) in inputs.iter().zip(args) {
+		match arg {
+			Value::Int(value) => commands.push(format!(
+				"scoreboard players set {} {}", input, value
+			)),
+			Value::Score(score) => if &score != input {
+				commands.push(format!("scoreboard players operation {} = {}", input, score));
+			}
+			// rejected when the arguments are resolved
+			Value::Target(_) | Value::Name(_) | Value::Resource(_) => unreachable!(),
+		}
+	}
+}
+
+// the input and output scores of a function
+pub fn params(function:&Function) -> CompileResult<(Vec<Score>, Vec<Score>)> {
+	let args = &function.args;
+	if args.input.is_empty() && args.output.is_empty() {
+		return CompileResult::Ok((Vec::new(), Vec::new()));
+	}
+	let Some(objective) = &args.objective else {
+		return CompileResult::Err(error(args.parens.clone(), ErrorInfo {
+			cause: "missing objective",
+			pointer: "the objective of the input and output scores is not defined",
+			context: Some("function args declaration"),
+			help: Some(Help {
+				msg: "add the objective after a colon".to_owned(),
+				source: "function add(a, b -> result : math) {}".to_owned(),
+				line: 0,
+			}),
+		}));
+	};
+	let score = |ident:&Ident| {
+		Score::new(ident.value.as_str(), objective.value.as_str())
+	};
+	CompileResult::Ok((args.input.iter().map(score).collect(), args.output.iter().map(score).collect()))
+}
+
+// the function or inline a call made in `module` refers to.
+// paths without a namespace are relative to the module of the caller,
+// or to the root of the datapack if nothing in that module matches
+pub fn find_callee<'d>(datapack:&'d Datapack, namespace:&str, module:&str, call:&Call) -> Option<Callee<'d>> {
+	let mut paths = Vec::new();
+	match &call.path.namespace {
+		Some(called) if called != namespace => return Some(Callee::External(call.path.to_string())),
+		Some(_) => {},
+		None => paths.push(module_path(module, &call.path.path)),
+	}
+	if !paths.contains(&call.path.path) {
+		paths.push(call.path.path.clone());
+	}
+	for path in paths {
+		if let Some(function) = datapack.functions.iter().find(|function| function.path() == path) {
+			return Some(Callee::Function(function));
+		}
+		if let Some(inline) = datapack.inlines.iter().find(|inline| inline.path() == path) {
+			return Some(Callee::Inline(inline));
+		}
+	}
+	None
+}
+
+// every call in a block
+pub fn collect_calls<'s>(statements:&'s [Statement], calls:&mut Vec<&'s Call>) {
+	for statement in statements {
+		match statement {
+			Statement::Call(call) => calls.extend(call.calls()),
+			// blocks are part of the function they're written in
+			Statement::At(at) => collect_calls(&at.body, calls),
+			Statement::Pipe(pipe) => calls.extend(pipe.value.calls()),
+			Statement::Let(local) => calls.extend(local.value.calls()),
+			Statement::Assign(assign) => calls.extend(assign.value.calls()),
+			Statement::If(if_statement) => {
+				calls.extend(if_statement.condition.calls());
+				collect_calls(&if_statement.body, calls);
+				if let Some(otherwise) = &if_statement.otherwise {
+					collect_calls(&otherwise.body, calls);
+				}
+			}
+			Statement::While(while_loop) => {
+				calls.extend(while_loop.condition.calls());
+				collect_calls(&while_loop.body, calls);
+			}
+			Statement::For(for_loop) => {
+				calls.extend(for_loop.start.calls());
+				calls.extend(for_loop.end.calls());
+				collect_calls(&for_loop.body, calls);
+			}
+			_ => {},
+		}
+	}
+}
+
+// the paths of the functions and inlines of the datapack every function and inline calls
+pub fn call_graph(namespace:&str, datapack:&Datapack) -> HashMap<String, Vec<String>> {
+	let functions = datapack.functions.iter()
+		.map(|function| (function.path(), &function.module, &function.body));
+	let inlines = datapack.inlines.iter()
+		.map(|inline| (inline.path(), &inline.module, &inline.body));
+
+	let mut graph = HashMap::new();
+	for (path, module, body) in functions.chain(inlines) {
+		let mut calls = Vec::new();
+		collect_calls(body, &mut calls);
+		let mut called:Vec<String> = Vec::new();
+		for call in calls {
+			// unknown functions are reported when the call is compiled
+			let Some(callee) = find_callee(datapack, namespace, module, call) else { continue };
+			if matches!(callee, Callee::External(_)) { continue }
+			let callee = callee.path();
+			if !called.contains(&callee) {
+				called.push(callee);
+			}
+		}
+		graph.insert(path, called);
+	}
+	graph
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::compiler::runner::{compile, Runner};
+
+	fn function(source:&str, name:&str) -> Vec<String> {
+		let path = format!("data/test/functions/{}.mcfunction", name);
+		let file = compile(source).into_iter()
+			.find(|file| file.path == path)
+			.unwrap_or_else(|| panic!("no function {}", name));
+		file.contents.lines().map(str::to_owned).collect()
+	}
+
+	const FACT:&str = "datapack test;
+		function fact(n -> r : math) {
+			if n <= 1 { r = 1; } else { r = n * fact(n - 1); }
+		}";
+
+	#[test]
+	fn recursive_calls_save_the_caller() {
+		let commands = function(FACT, "__fact_2");
+		let call = commands.iter().position(|command| command == "function test:fact").unwrap();
+		// every score of the caller is pushed before the argument is passed
+		assert_eq!(commands[call-7], "data modify storage test:__stack frames append value {}");
+		assert_eq!(commands[call-6], "execute store result storage test:__stack frames[-1].s0 int 1 run scoreboard players get n math");
+		assert_eq!(commands[call-1], "scoreboard players operation n math = #t2 __test+fact");
+		// the output is moved out of the way before `r` is restored
+		assert_eq!(commands[call+1], "scoreboard players operation #ret __test+fact = r math");
+		assert_eq!(commands[call+2], "execute store result score n math run data get storage test:__stack frames[-1].s0");
+		assert_eq!(commands[call+7], "data remove storage test:__stack frames[-1]");
+		assert_eq!(commands[call+8], "scoreboard players operation #t1 __test+fact *= #ret __test+fact");
+
+		let mut runner = Runner::new(FACT);
+		runner.set("n", "math", 5);
+		runner.call("test:fact");
+		assert_eq!(runner.get("r", "math"), 120);
+		assert_eq!(runner.get("n", "math"), 5);
+	}
+
+	#[test]
+	fn recursive_calls_in_one_expression() {
+		let mut runner = Runner::new("datapack test;
+			function fib(n -> r : math) {
+				if n < 2 { r = n; } else { r = fib(n - 1) + fib(n - 2); }
+			}");
+		for (n, fib) in [(0, 0), (1, 1), (2, 1), (7, 13), (12, 144)] {
+			runner.set("n", "math", n);
+			runner.call("test:fib");
+			assert_eq!(runner.get("r", "math"), fib);
+			assert_eq!(runner.get("n", "math"), n);
+		}
+	}
+
+	#[test]
+	fn swapped_args_are_copied_first() {
+		let source = "datapack test;
+			function sub(a, b -> r : math) { r = a - b; }
+			function swap(a, b -> r : math) { r = sub(b, a); }";
+		let commands = function(source, "swap");
+		assert!(commands.contains(&"scoreboard players operation #arg0 __test+swap = b math".to_owned()));
+		assert!(commands.contains(&"scoreboard players operation #arg1 __test+swap = a math".to_owned()));
+		assert!(commands.contains(&"scoreboard players operation a math = #arg0 __test+swap".to_owned()));
+
+		let mut runner = Runner::new(source);
+		runner.set("a", "math", 10);
+		runner.set("b", "math", 3);
+		runner.call("test:swap");
+		assert_eq!(runner.get("r", "math"), -7);
+		assert_eq!((runner.get("a", "math"), runner.get("b", "math")), (10, 3));
+	}
+
+	#[test]
+	fn args_in_order_are_not_copied() {
+		let source = "datapack test;
+			function sub(a, b -> r : math) { r = a - b; }
+			function same(a, b -> r : math) { r = sub(a, b); }";
+		let commands = function(source, "same");
+		assert!(!commands.iter().any(|command| command.contains("#arg")));
+		assert!(!commands.iter().any(|command| command.contains("= a math") || command.contains("= b math")));
+	}
+
+	#[test]
+	fn at_blocks_narrow_the_executor() {
+		let source = "datapack test;
+			function kill_player() at player {}
+			function cleanup() {
+				kill_player();
+				at @s[type=player] { kill_player(); }
+				at @s { kill_player(); }
+			}";
+		assert_eq!(function(source, "cleanup")[0], "execute if entity @s[type=player] run function test:kill_player");
+		assert_eq!(function(source, "__cleanup_1"), vec!["function test:kill_player"]);
+		assert_eq!(function(source, "__cleanup_2"), vec!["execute if entity @s[type=player] run function test:kill_player"]);
+	}
+
+	#[test]
+	fn calls_without_shared_scores_save_nothing() {
+		let source = "datapack test;
+			function double(x -> y : math) { y = x * 2; }
+			function main() { let z = 0; |d = double(4); z = |d; }";
+		let commands = function(source, "main");
+		assert!(!commands.iter().any(|command| command.contains("__stack")));
+		assert!(!commands.iter().any(|command| command.contains("#ret")));
+		assert!(commands.contains(&"scoreboard players set x math 4".to_owned()));
+	}
+}
@@ -0,0 +1,449 @@
+use crate::compiler::Compiler;
+use crate::compiler::frame::{Frame, Score, Value};
+use crate::debug::errors::{error, ErrorInfo};
+use crate::lexer::tokens::{Ident, Token};
+use crate::parser::{Arg, Assign, Binary, Compare, Comparison, Expr, InRange, Let, Operator, Pipe};
+
+impl<'a> Compiler<'a> {
+	// an integer or a score, a single identifier can also be a parameter of the function
+	pub(super) fn value(&self, frame:&Frame, arg:&Arg, context:&str) -> Value {
+		if let [Token::Ident(name)] = arg.tokens.as_slice() {
+			if let Some(score) = frame.param(name.value.as_str()) {
+				return Value::Score(score);
+			}
+		}
+		match self.arg_value(frame, arg, context) {
+			value @ (Value::Int(_) | Value::Score(_)) => value,
+			_ => self.fatal(error(arg.scope(), ErrorInfo {
+				cause: "invalid value",
+				pointer: "expected an integer, a local, a pipe, a parameter or a score",
+				context: Some(context),
+				help: None,
+			})),
+		}
+	}
+
+	// the value of an expression, an integer or a score,
+	// the temporaries it's computed in are added to `temps`
+	pub(super) fn eval(
+		&mut self,
+		frame:&mut Frame,
+		expr:&Expr,
+		context:&str,
+		temps:&mut Vec<Score>,
+		commands:&mut Vec<String>,
+	) -> Value {
+		match expr {
+			Expr::Value(arg) => self.value(frame, arg, context),
+			Expr::Call(call) => {
+				let used = Some((expr.scope(), context));
+				Value::Score(self.compile_call_output(frame, call, used, commands).unwrap())
+			}
+			Expr::Paren(_, expr) => self.eval(frame, expr, context, temps, commands),
+			Expr::Neg(_, expr) => match self.eval(frame, expr, context, temps, commands) {
+				Value::Int(int) => Value::Int(int.wrapping_neg()),
+				value => {
+					let result = temp(frame, temps);
+					commands.push(format!("scoreboard players set {} 0", result));
+					apply(frame, &result, Operator::Sub, value, temps, commands);
+					Value::Score(result)
+				}
+			}
+			Expr::Binary(binary) => {
+				let lhs = self.eval(frame, &binary.lhs, context, temps, commands);
+				let lhs = keep_for(frame, lhs, &binary.rhs, temps, commands);
+				let rhs = self.eval(frame, &binary.rhs, context, temps, commands);
+				if let (Value::Int(lhs), Value::Int(rhs)) = (&lhs, &rhs) {
+					return Value::Int(self.fold(binary, *lhs, *rhs, context));
+				}
+				self.check_divisor(binary, &rhs, context);
+				// a temporary on the left is reused for the result
+				let result = match lhs {
+					Value::Score(score) if temps.contains(&score) => score,
+					lhs => {
+						let result = temp(frame, temps);
+						set_score(&result, lhs, commands);
+						result
+					}
+				};
+				apply(frame, &result, binary.op, rhs, temps, commands);
+				Value::Score(result)
+			}
+			Expr::Compare(compare) => {
+				let check = self.compare(frame, compare, context, temps, commands);
+				let result = temp(frame, temps);
+				commands.push(format!("execute store success score {} {}", result, check));
+				Value::Score(result)
+			}
+			Expr::In(in_range) => {
+				let check = self.in_range(frame, in_range, context, temps, commands);
+				let result = temp(frame, temps);
+				commands.push(format!("execute store success score {} {}", result, check));
+				Value::Score(result)
+			}
+		}
+	}
+
+	// computes an expression straight into `target` when that doesn't change what it reads
+	pub(super) fn eval_into(
+		&mut self,
+		frame:&mut Frame,
+		expr:&Expr,
+		target:&Score,
+		context:&str,
+		temps:&mut Vec<Score>,
+		commands:&mut Vec<String>,
+	) {
+		if let Expr::Paren(_, expr) = expr {
+			return self.eval_into(frame, expr, target, context, temps, commands);
+		}
+		if let Expr::Binary(binary) = expr {
+			let simple = binary.rhs.calls().is_empty() && !self.reads(frame, &binary.rhs, target, context);
+			if simple && self.constant(frame, expr, context).is_none() {
+				self.eval_into(frame, &binary.lhs, target, context, temps, commands);
+				let rhs = self.eval(frame, &binary.rhs, context, temps, commands);
+				self.check_divisor(binary, &rhs, context);
+				apply(frame, target, binary.op, rhs, temps, commands);
+				return;
+			}
+		}
+		let value = self.eval(frame, expr, context, temps, commands);
+		set_score(target, value, commands);
+	}
+
+	// whether evaluating an expression reads a score
+	fn reads(&self, frame:&Frame, expr:&Expr, score:&Score, context:&str) -> bool {
+		match expr {
+			Expr::Value(arg) => match self.value(frame, arg, context) {
+				Value::Score(value) => &value == score,
+				_ => false,
+			}
+			// what a call reads is checked by the callers of `reads`
+			Expr::Call(_) => false,
+			Expr::Paren(_, expr) | Expr::Neg(_, expr) => self.reads(frame, expr, score, context),
+			Expr::Binary(binary) => {
+				self.reads(frame, &binary.lhs, score, context) || self.reads(frame, &binary.rhs, score, context)
+			}
+			Expr::Compare(compare) => {
+				self.reads(frame, &compare.lhs, score, context) || self.reads(frame, &compare.rhs, score, context)
+			}
+			Expr::In(in_range) => self.reads(frame, &in_range.lhs, score, context),
+		}
+	}
+
+	// the value of an expression that only uses integers
+	pub(super) fn constant(&self, frame:&Frame, expr:&Expr, context:&str) -> Option<i32> {
+		match expr {
+			Expr::Value(arg) => match self.value(frame, arg, context) {
+				Value::Int(int) => Some(int),
+				_ => None,
+			}
+			Expr::Paren(_, expr) => self.constant(frame, expr, context),
+			Expr::Neg(_, expr) => self.constant(frame, expr, context).map(i32::wrapping_neg),
+			Expr::Binary(binary) => {
+				let lhs = self.constant(frame, &binary.lhs, context)?;
+				let rhs = self.constant(frame, &binary.rhs, context)?;
+				Some(self.fold(binary, lhs, rhs, context))
+			}
+			Expr::Call(_) | Expr::Compare(_) | Expr::In(_) => None,
+		}
+	}
+
+	// Minecraft leaves a score unchanged when it's divided by 0
+	fn check_divisor(&self, binary:&Binary, rhs:&Value, context:&str) {
+		if let Value::Int(0) = rhs {
+			if matches!(binary.op, Operator::Div | Operator::Rem) {
+				self.fatal(error(binary.rhs.scope(), ErrorInfo {
+					cause: "division by zero",
+					pointer: "this is always 0",
+					context: Some(context),
+					help: None,
+				}));
+			}
+		}
+	}
+
+	// computes an operation on integers like Minecraft does
+	fn fold(&self, binary:&Binary, lhs:i32, rhs:i32, context:&str) -> i32 {
+		self.check_divisor(binary, &Value::Int(rhs), context);
+		match binary.op {
+			Operator::Add => lhs.wrapping_add(rhs),
+			Operator::Sub => lhs.wrapping_sub(rhs),
+			Operator::Mul => lhs.wrapping_mul(rhs),
+			Operator::Div => floor_div(lhs, rhs),
+			Operator::Rem => lhs.wrapping_sub(floor_div(lhs, rhs).wrapping_mul(rhs)),
+		}
+	}
+
+	// the ranges of conditions are the ranges of `execute if score ... matches`
+	fn in_range(
+		&mut self,
+		frame:&mut Frame,
+		in_range:&InRange,
+		context:&str,
+		temps:&mut Vec<Score>,
+		commands:&mut Vec<String>,
+	) -> String {
+		let value = self.eval(frame, &in_range.lhs, context, temps, commands);
+		let score = into_score(frame, value, temps, commands);
+		format!("if score {} matches {}", score, in_range.range.text())
+	}
+
+	// compiles a comparison, and returns the `if`/`unless` part of an execute command
+	fn compare(
+		&mut self,
+		frame:&mut Frame,
+		compare:&Compare,
+		context:&str,
+		temps:&mut Vec<Score>,
+		commands:&mut Vec<String>,
+	) -> String {
+		let lhs = self.eval(frame, &compare.lhs, context, temps, commands);
+		let lhs = keep_for(frame, lhs, &compare.rhs, temps, commands);
+		let rhs = self.eval(frame, &compare.rhs, context, temps, commands);
+		let cmp = compare.cmp;
+		match (lhs, rhs) {
+			(Value::Score(lhs), Value::Int(rhs)) => score_matches(frame, &lhs, cmp, rhs, temps, commands),
+			(Value::Int(lhs), Value::Score(rhs)) => score_matches(frame, &rhs, cmp.flip(), lhs, temps, commands),
+			(Value::Int(lhs), Value::Int(rhs)) => {
+				let lhs = into_score(frame, Value::Int(lhs), temps, commands);
+				score_matches(frame, &lhs, cmp, rhs, temps, commands)
+			}
+			(lhs, rhs) => {
+				let lhs = into_score(frame, lhs, temps, commands);
+				let rhs = into_score(frame, rhs, temps, commands);
+				compare_scores(&lhs, cmp, &rhs)
+			}
+		}
+	}
+
+	// compiles a condition, and returns the `if`/`unless` part of an execute command,
+	// anything that isn't a comparison is true when it isn't 0
+	pub(super) fn compile_condition(&mut self, frame:&mut Frame, condition:&Expr, commands:&mut Vec<String>) -> String {
+		let mut temps = Vec::new();
+		let mut expr = condition;
+		while let Expr::Paren(_, inner) = expr { expr = inner }
+		let check = match expr {
+			Expr::Compare(compare) => self.compare(frame, compare, "condition", &mut temps, commands),
+			Expr::In(in_range) => self.in_range(frame, in_range, "condition", &mut temps, commands),
+			expr => {
+				let value = self.eval(frame, expr, "condition", &mut temps, commands);
+				let score = into_score(frame, value, &mut temps, commands);
+				format!("unless score {} matches 0", score)
+			}
+		};
+		free_all(frame, &temps);
+		check
+	}
+
+	pub(super) fn compile_let(&mut self, frame:&mut Frame, local:&Let, commands:&mut Vec<String>) {
+		let holder = self.new_local(frame, &local.name);
+		// the value is computed before the local is declared, so it can't use it
+		let mut temps = Vec::new();
+		self.eval_into(frame, &local.value, &holder, "local declaration", &mut temps, commands);
+		free_all(frame, &temps);
+		frame.declare(&local.name);
+	}
+
+	// the holder of a local that is about to be declared
+	pub(super) fn new_local(&self, frame:&Frame, name:&Ident) -> Score {
+		if name.value.starts_with('$') {
+			self.fatal(error(name.scope.clone(), ErrorInfo {
+				cause: "invalid local name",
+				pointer: "names starting with `$` are used by inline arguments",
+				context: Some("local declaration"),
+				help: None,
+			}));
+		}
+		if let Some(existing) = frame.find_local(name.value.as_str()) {
+			self.fatal(error(name.scope.clone(), ErrorInfo {
+				cause: "shadowed local",
+				pointer: "a local with this name is already in scope",
+				context: Some("local declaration"),
+				help: None,
+			}).note(existing.scope.clone(), "declared here"));
+		}
+		if frame.param(name.value.as_str()).is_some() {
+			self.fatal(error(name.scope.clone(), ErrorInfo {
+				cause: "shadowed parameter",
+				pointer: "the function already has a parameter with this name",
+				context: Some("local declaration"),
+				help: None,
+			}));
+		}
+		Score::new(name.value.as_str(), frame.objective.as_str())
+	}
+
+	pub(super) fn compile_assign(&mut self, frame:&mut Frame, assign:&Assign, commands:&mut Vec<String>) {
+		let target = match self.arg_target(frame, &assign.target, "assignment") {
+			Value::Score(score) => score,
+			_ => self.fatal(error(assign.target.scope(), ErrorInfo {
+				cause: "invalid assignment",
+				pointer: "only a local, a pipe, a parameter or a score can be assigned to",
+				context: Some("assignment"),
+				help: None,
+			})),
+		};
+		let mut temps = Vec::new();
+		match assign.op {
+			None => self.eval_into(frame, &assign.value, &target, "assignment", &mut temps, commands),
+			Some(op) => {
+				let value = self.eval(frame, &assign.value, "assignment", &mut temps, commands);
+				apply(frame, &target, op, value, &mut temps, commands);
+			}
+		}
+		free_all(frame, &temps);
+	}
+
+	fn arg_target(&self, frame:&Frame, arg:&Arg, context:&str) -> Value {
+		if let [Token::Ident(name)] = arg.tokens.as_slice() {
+			if let Some(score) = frame.param(name.value.as_str()) {
+				return Value::Score(score);
+			}
+		}
+		self.arg_value(frame, arg, context)
+	}
+
+	// a pipe that holds a call's output, or the value of an expression
+	pub(super) fn compile_pipe(&mut self, frame:&mut Frame, pipe:&Pipe, commands:&mut Vec<String>) {
+		if let Expr::Call(call) = &pipe.value {
+			self.compile_call(frame, call, Some(pipe), commands);
+			return;
+		}
+		self.set_pipe(frame, &pipe.name, &pipe.value, "pipe declaration", commands);
+	}
+
+	pub(super) fn set_pipe(&mut self, frame:&mut Frame, name:&Ident, expr:&Expr, context:&str, commands:&mut Vec<String>) -> Score {
+		let mut temps = Vec::new();
+		let holder = match frame.pipe(name) {
+			Some(holder) => {
+				self.eval_into(frame, expr, &holder, context, &mut temps, commands);
+				holder
+			}
+			// the value can't use the pipe before it's declared
+			None => {
+				let value = self.eval(frame, expr, context, &mut temps, commands);
+				let holder = frame.bind_pipe(name);
+				set_score(&holder, value, commands);
+				holder
+			}
+		};
+		free_all(frame, &temps);
+		holder
+	}
+}
+
+fn temp(frame:&mut Frame, temps:&mut Vec<Score>) -> Score {
+	let temp = frame.temp();
+	temps.push(temp.clone());
+	temp
+}
+
+// gives a temporary back to the frame, so the rest of the statement can reuse it
+fn release(frame:&mut Frame, temps:&mut Vec<Score>, score:&Score) {
+	if let Some(i) = temps.iter().position(|temp| temp == score) {
+		temps.remove(i);
+		frame.free(score);
+	}
+}
+
+pub fn free_all(frame:&mut Frame, temps:&[Score]) {
+	for temp in temps {
+		frame.free(temp);
+	}
+}
+
+// the value on the left is read after a call on the right runs, which could change it
+pub fn keep_for(frame:&mut Frame, lhs:Value, rhs:&Expr, temps:&mut Vec<Score>, commands:&mut Vec<String>) -> Value {
+	match lhs {
+		Value::Score(score) if !temps.contains(&score) && !rhs.calls().is_empty() => {
+			let copy = temp(frame, temps);
+			commands.push(format!("scoreboard players operation {} = {}", copy, score));
+			Value::Score(copy)
+		}
+		lhs => lhs,
+	}
+}
+
+pub fn into_score(frame:&mut Frame, value:Value, temps:&mut Vec<Score>, commands:&mut Vec<String>) -> Score {
+	match value {
+		Value::Score(score) => score,
+		Value::Int(int) => {
+			let score = temp(frame, temps);
+			commands.push(format!("scoreboard players set {} {}", score, int));
+			score
+		}
+		// rejected when the values are resolved
+		Value::Target(_) | Value::Name(_) | Value::Resource(_) => unreachable!(),
+	}
+}
+
+pub fn set_score(target:&Score, value:Value, commands:&mut Vec<String>) {
+	match value {
+		Value::Int(int) => commands.push(format!("scoreboard players set {} {}", target, int)),
+		Value::Score(score) => if &score != target {
+			commands.push(format!("scoreboard players operation {} = {}", target, score));
+		}
+		Value::Target(_) | Value::Name(_) | Value::Resource(_) => unreachable!(),
+	}
+}
+
+// `target op= value`
+fn apply(frame:&mut Frame, target:&Score, op:Operator, value:Value, temps:&mut Vec<Score>, commands:&mut Vec<String>) {
+	match (op, value) {
+		// `add` and `remove` only take positive numbers
+		(Operator::Add, Value::Int(int)) | (Operator::Sub, Value::Int(int)) if int != i32::MIN => {
+			let add = (op == Operator::Add) == (int >= 0);
+			let keyword = if add { "add" } else { "remove" };
+			commands.push(format!("scoreboard players {} {} {}", keyword, target, int.abs()));
+		}
+		(op, value) => {
+			let score = into_score(frame, value, temps, commands);
+			commands.push(format!("scoreboard players operation {} {} {}", target, op.operation(), score));
+			release(frame, temps, &score);
+		}
+	}
+}
+
+fn floor_div(lhs:i32, rhs:i32) -> i32 {
+	let div = lhs.wrapping_div(rhs);
+	if lhs.wrapping_rem(rhs) != 0 && (lhs < 0) != (rhs < 0) { div - 1 } else { div }
+}
+
+fn score_matches(
+	frame:&mut Frame,
+	score:&Score,
+	cmp:Comparison,
+	int:i32,
+	temps:&mut Vec<Score>,
+	commands:&mut Vec<String>,
+) -> String {
+	let range = match cmp {
+		Comparison::Eq | Comparison::Ne => Some(format!("{}", int)),
+		Comparison::Lt => int.checked_sub(1).map(|max| format!("..{}", max)),
+		Comparison::Le => Some(format!("..{}", int)),
+		Comparison::Gt => int.checked_add(1).map(|min| format!("{}..", min)),
+		Comparison::Ge => Some(format!("{}..", int)),
+	};
+	match range {
+		Some(range) if cmp == Comparison::Ne => format!("unless score {} matches {}", score, range),
+		Some(range) => format!("if score {} matches {}", score, range),
+		// the bound doesn't fit in a range, so the int is compared as a score instead
+		None => {
+			let rhs = into_score(frame, Value::Int(int), temps, commands);
+			compare_scores(score, cmp, &rhs)
+		}
+	}
+}
+
+fn compare_scores(lhs:&Score, cmp:Comparison, rhs:&Score) -> String {
+	let op = match cmp {
+		Comparison::Eq | Comparison::Ne => "=",
+		Comparison::Lt => "<",
+		Comparison::Le => "<=",
+		Comparison::Gt => ">",
+		Comparison::Ge => ">=",
+	};
+	let keyword = if cmp == Comparison::Ne { "unless" } else { "if" };
+	format!("{} score {} {} {}", keyword, lhs, op, rhs)
+}
@@ -0,0 +1,227 @@
+use std::fmt::{Display, Formatter};
+use crate::lexer::tokens::{Ident, Selector};
+use crate::parser::{module_path, AtLoc};
+
+// the state of the function that is being compiled
+pub struct Frame {
+	// the path of the function from the root of the datapack
+	pub function: String,
+	pub module: String,
+	pub name: String,
+	// the objective that holds the pipes and temporary values of this function
+	pub objective: String,
+	// the input and output scores of this function
+	pub params: Vec<Score>,
+	pub pipes: Vec<String>,
+	// the scratch holders that are in use
+	pub temps: Vec<String>,
+	// other holders that keep their value across statements
+	pub kept: Vec<String>,
+	// the locals declared in every block that is being compiled, innermost last
+	pub scopes: Vec<Vec<Ident>>,
+	// what `@s` is while this function runs
+	pub executor: Executor,
+	// more of this function runs after the block being compiled, without waiting for a loop in it
+	pub followed: bool,
+	pub used: bool,
+	// the number of sub-functions generated for the blocks in this function
+	pub blocks: usize,
+}
+impl Frame {
+	pub fn new(namespace:&str, module:&str, name:&str, params:Vec<Score>, executor:Executor) -> Self {
+		let function = module_path(module, name);
+		Frame {
+			// objectives can't contain `:` or `/`, but `+` can't be part of a resource location,
+			// so the objectives of functions in different modules and datapacks never clash
+			objective: format!("__{}+{}", namespace, function.replace('/', "+")),
+			function,
+			module: module.to_owned(),
+			name: name.to_owned(),
+			params,
+			pipes: Vec::new(),
+			temps: Vec::new(),
+			kept: Vec::new(),
+			scopes: Vec::new(),
+			executor,
+			followed: false,
+			used: false,
+			blocks: 0,
+		}
+	}
+
+	// the path of the next function generated for a block in this function,
+	// it's placed in the same module
+	pub fn block_function(&mut self) -> String {
+		self.blocks += 1;
+		module_path(&self.module, format!("__{}_{}", self.name, self.blocks).as_str())
+	}
+
+	pub fn holder(&mut self, name:&str) -> Score {
+		self.used = true;
+		Score::new(name, self.objective.as_str())
+	}
+
+	pub fn enter_scope(&mut self) {
+		self.scopes.push(Vec::new());
+	}
+	pub fn exit_scope(&mut self) {
+		self.scopes.pop();
+	}
+	// locals are held by their name, which can't clash with the other holders
+	pub fn declare(&mut self, name:&Ident) -> Score {
+		self.scopes.last_mut().unwrap().push(name.clone());
+		self.holder(name.value.as_str())
+	}
+	// the declaration of a local that is in scope
+	pub fn find_local(&self, name:&str) -> Option<&Ident> {
+		self.scopes.iter().flatten().find(|local| local.value == name)
+	}
+	pub fn local(&self, name:&str) -> Option<Score> {
+		self.find_local(name)?;
+		Some(Score::new(name, self.objective.as_str()))
+	}
+
+	pub fn param(&self, name:&str) -> Option<Score> {
+		self.params.iter().find(|param| param.holder == name).cloned()
+	}
+
+	pub fn bind_pipe(&mut self, name:&Ident) -> Score {
+		if !self.pipes.contains(&name.value) {
+			self.pipes.push(name.value.clone());
+		}
+		self.holder(format!("|{}", name.value).as_str())
+	}
+	pub fn pipe(&self, name:&Ident) -> Option<Score> {
+		if !self.pipes.contains(&name.value) { return None }
+		Some(Score::new(format!("|{}", name.value).as_str(), self.objective.as_str()))
+	}
+
+	// a holder that is part of the state of this function
+	pub fn keep(&mut self, name:&str) -> Score {
+		if !self.kept.iter().any(|kept| kept == name) {
+			self.kept.push(name.to_owned());
+		}
+		self.holder(name)
+	}
+
+	// a scratch holder that is reserved until it's freed,
+	// holders that were freed before are reused
+	pub fn temp(&mut self) -> Score {
+		let mut i = 0;
+		while self.temps.contains(&format!("#t{}", i)) { i += 1 }
+		let name = format!("#t{}", i);
+		self.temps.push(name.clone());
+		self.holder(name.as_str())
+	}
+	pub fn free(&mut self, temp:&Score) {
+		self.temps.retain(|name| name != &temp.holder);
+	}
+
+	// every score this function needs to keep when it calls itself
+	pub fn state(&self) -> Vec<Score> {
+		let mut state = self.params.clone();
+		for pipe in &self.pipes {
+			state.push(Score::new(format!("|{}", pipe).as_str(), self.objective.as_str()));
+		}
+		for holder in self.temps.iter().chain(&self.kept) {
+			state.push(Score::new(holder.as_str(), self.objective.as_str()));
+		}
+		for local in self.scopes.iter().flatten() {
+			state.push(Score::new(local.value.as_str(), self.objective.as_str()));
+		}
+		state
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Executor {
+	// run by the game, there is no `@s`
+	Server,
+	// `@s` is known to match this location
+	Known(AtLoc),
+	// the function could be run from anywhere
+	Unknown,
+}
+impl Executor {
+	// whether running something guarded with `at <loc>` in this context
+	// is always fine, never fine, or has to be checked
+	pub fn satisfies(&self, loc:&AtLoc) -> Option<bool> {
+		match (self, loc) {
+			(_, AtLoc::Any) => Some(true),
+			(Self::Server, _) => Some(false),
+			(Self::Known(AtLoc::Player | AtLoc::Players), _) => Some(true),
+			(Self::Known(AtLoc::Entity | AtLoc::Entities), AtLoc::Entity | AtLoc::Entities) => Some(true),
+			_ => None,
+		}
+	}
+
+	// what `@s` is after `execute as <selector>`
+	pub fn of_selector(&self, selector:&Selector) -> Executor {
+		let players = selector.players();
+		match selector.base {
+			// `@s[type=player]` only matches when the executor is a player
+			's' if players => Self::Known(AtLoc::Player),
+			's' => self.clone(),
+			'a' => Self::Known(AtLoc::Players),
+			'p' | 'r' => Self::Known(AtLoc::Player),
+			'e' if players => Self::Known(AtLoc::Players),
+			'e' => Self::Known(AtLoc::Entities),
+			_ => Self::Unknown,
+		}
+	}
+
+	pub fn describe(&self) -> &'static str {
+		match self {
+			Self::Server => "the server",
+			Self::Known(loc) => loc.describe(),
+			Self::Unknown => "anything",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+	pub holder: String,
+	pub objective: String,
+}
+impl Score {
+	pub fn new(holder:&str, objective:&str) -> Self {
+		Score { holder: holder.to_owned(), objective: objective.to_owned() }
+	}
+}
+impl Display for Score {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {}", self.holder, self.objective)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+	Int(i32),
+	Score(Score),
+	Target(Selector),
+	// a single identifier, which could be a score holder or an objective
+	Name(String),
+	Resource(String),
+}
+impl Value {
+	// the text this value is substituted with in commands
+	pub fn text(&self) -> String {
+		match self {
+			Self::Int(value) => value.to_string(),
+			Self::Score(score) => score.to_string(),
+			Self::Target(target) => target.to_string(),
+			Self::Name(name) => name.clone(),
+			Self::Resource(loc) => loc.clone(),
+		}
+	}
+	pub fn describe(&self) -> &'static str {
+		match self {
+			Self::Int(_) => "an int",
+			Self::Score(_) => "a score",
+			Self::Target(_) => "a target",
+			Self::Name(_) => "a name",
+			Self::Resource(_) => "a resource location",
+		}
+	}
+}
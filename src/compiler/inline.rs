@@ -0,0 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+use crate::compiler::Compiler;
+use crate::compiler::frame::{Frame, Score, Value};
+use crate::debug::errors::{error, ErrorInfo};
+use crate::lexer::tokens::SrcScope;
+use crate::parser::{Call, Expr, Inline, InlineArgType, UnsafeCmd};
+
+// an inline that is being expanded
+pub struct Expansion {
+	// the path of the inline
+	pub name: String,
+	// the module calls in the inline are resolved from
+	pub module: String,
+	pub call: SrcScope,
+	pub args: HashMap<String, Value>,
+}
+
+impl<'a> Compiler<'a> {
+	pub(super) fn compile_inline_call(
+		&mut self,
+		frame:&mut Frame,
+		call:&Call,
+		inline:&'a Inline,
+		commands:&mut Vec<String>,
+	) {
+		let scope = call.path.scope.join(call.group.scope.clone());
+		let path = inline.path();
+		if self.expansions.iter().any(|expansion| expansion.name == path) {
+			self.fatal(error(scope, ErrorInfo {
+				cause: "recursive inline",
+				pointer: "this inline ends up expanding itself",
+				context: Some("inline call"),
+				help: None,
+			}));
+		}
+
+		let mut values = VecDeque::new();
+		// inlines get their arguments as they are written, so they can't be computed
+		for arg in &call.args {
+			let Expr::Value(arg) = arg else {
+				self.fatal(error(arg.scope(), ErrorInfo {
+					cause: "invalid argument",
+					pointer: "the arguments of inlines are used as they are written, they can't be expressions",
+					context: Some("inline call"),
+					help: None,
+				}))
+			};
+			values.push_back((self.arg_value(frame, arg, "inline call"), arg.scope()));
+		}
+		let args = self.bind_inline_args(call, inline, values);
+
+		self.expansions.push(Expansion {
+			name: path,
+			module: inline.module.clone(),
+			call: scope,
+			args,
+		});
+		let body = self.compile_block(frame, &inline.body);
+		commands.extend(body);
+		self.expansions.pop();
+	}
+
+	// matches the values passed to an inline with its arguments.
+	// a score fills a `target` and the `objective` after it, and a `score` can be given as a target and an objective
+	fn bind_inline_args(
+		&self,
+		call:&Call,
+		inline:&Inline,
+		mut values:VecDeque<(Value, SrcScope)>,
+	) -> HashMap<String, Value> {
+		let mut args = HashMap::new();
+
+		for param in &inline.args {
+			let Some((value, scope)) = values.pop_front() else {
+				self.fatal(error(call.group.scope.clone(), ErrorInfo {
+					cause: "missing argument",
+					pointer: format!(
+						"expected a value for `{}`, which is {}",
+						param.name.value, param.ty.describe()
+					).as_str(),
+					context: Some("inline call"),
+					help: None,
+				}));
+			};
+			let value = match (&param.ty, value) {
+				(InlineArgType::Int, Value::Int(value)) => Value::Int(value),
+				(InlineArgType::Target, Value::Target(target)) => Value::Target(target),
+				(InlineArgType::Target, Value::Name(name)) => Value::Name(name),
+				(InlineArgType::Target, Value::Score(score)) => {
+					values.push_front((Value::Name(score.objective), scope));
+					Value::Name(score.holder)
+				}
+				(InlineArgType::Objective, Value::Name(name)) => Value::Name(name),
+				(InlineArgType::Resource, Value::Resource(loc)) => Value::Resource(loc),
+				(InlineArgType::Resource, Value::Name(name)) => Value::Resource(name),
+				(InlineArgType::Score, Value::Score(score)) => Value::Score(score),
+				(InlineArgType::Score, value @ (Value::Target(_) | Value::Name(_))) => {
+					let holder = value.text();
+					let objective = match values.pop_front() {
+						Some((Value::Name(objective), _)) => objective,
+						_ => self.fatal(error(scope, ErrorInfo {
+							cause: "invalid argument",
+							pointer: format!(
+								"`{}` is a score, so this target must be followed by an objective",
+								param.name.value
+							).as_str(),
+							context: Some("inline call"),
+							help: None,
+						})),
+					};
+					Value::Score(Score { holder, objective })
+				}
+				(ty, value) => self.fatal(error(scope, ErrorInfo {
+					cause: "mismatched types",
+					pointer: format!(
+						"`{}` is {}, found {}",
+						param.name.value, ty.describe(), value.describe()
+					).as_str(),
+					context: Some("inline call"),
+					help: None,
+				}).note(param.ty_scope.clone(), "argument declared here")),
+			};
+			args.insert(param.name.value.clone(), value);
+		}
+
+		if let Some((_, scope)) = values.pop_front() {
+			self.fatal(error(scope, ErrorInfo {
+				cause: "too many arguments",
+				pointer: format!(
+					"`{}` doesn't take any more arguments", inline.name.value
+				).as_str(),
+				context: Some("inline call"),
+				help: None,
+			}));
+		}
+		args
+	}
+
+	// the value of `$name` in the inline that is being expanded
+	pub(super) fn inline_arg(&self, name:&str, scope:SrcScope) -> Value {
+		let value = self.expansions.last()
+			.and_then(|expansion| expansion.args.get(name));
+		match value {
+			Some(value) => value.clone(),
+			None => self.fatal(error(scope, ErrorInfo {
+				cause: "unknown inline argument",
+				pointer: format!("`${}` is not an argument of the inline being expanded", name).as_str(),
+				context: Some("inline expansion"),
+				help: None,
+			})),
+		}
+	}
+
+	pub(super) fn unsafe_command(&self, command:&UnsafeCmd) -> String {
+		let raw = &command.raw;
+		let text = if self.expansions.is_empty() {
+			raw.value.clone()
+		} else {
+			self.substitute(raw.value.as_str(), raw.scope.clone())
+		};
+		// a command written across multiple lines is joined back into one
+		if !text.contains('\n') { return text }
+		text.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+	}
+
+	// replaces every `$name` in the text with the argument of the inline being expanded,
+	// expects `text` to be the source in `scope`
+	fn substitute(&self, text:&str, scope:SrcScope) -> String {
+		let chars:Vec<char> = text.chars().collect();
+		let mut result = String::new();
+		let mut i = 0;
+		while i < chars.len() {
+			let c = chars[i];
+			i += 1;
+			if c != '$' || i >= chars.len() || !(chars[i].is_alphabetic() || chars[i] == '_') {
+				result.push(c);
+				continue;
+			}
+			let start = i-1;
+			let mut name = String::new();
+			while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+				name.push(chars[i]);
+				i += 1;
+			}
+			let name_scope = SrcScope {
+				start: scope.start+start,
+				end: scope.start+i,
+				file: scope.file.clone(),
+			};
+			result.push_str(self.inline_arg(name.as_str(), name_scope).text().as_str());
+		}
+		result
+	}
+}
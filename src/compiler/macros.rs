@@ -0,0 +1,125 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::compiler::Compiler;
+use crate::compiler::frame::Frame;
+use crate::compiler::text::BUILTIN_MACROS;
+use crate::debug::errors::{error, warning, ErrorInfo};
+use crate::parser::{Macro, MacroCall};
+
+// how long an external macro can run before its expansion fails
+pub const MACRO_TIMEOUT:Duration = Duration::from_secs(5);
+
+impl<'a> Compiler<'a> {
+	// runs the executable of the macro with the input of the call,
+	// every line it prints is a command
+	pub(super) fn compile_macro(&self, frame:&Frame, call:&MacroCall, commands:&mut Vec<String>) {
+		let Some(external) = self.datapack.macros.iter()
+			.find(|external| external.name.value == call.name.value)
+		else {
+			if BUILTIN_MACROS.contains(&call.name.value.as_str()) {
+				self.compile_tellraw(frame, call, commands);
+				return;
+			}
+			self.fatal(error(call.name.scope.clone(), ErrorInfo {
+				cause: "unknown macro",
+				pointer: "no macro with this name has been declared",
+				context: Some("macro call"),
+				help: None,
+			}))
+		};
+		let scope = call.name.scope.join(call.group.scope.clone());
+		let output = match run_macro(external, call.input().as_str()) {
+			Ok(output) => output,
+			Err(msg) => self.fatal(error(scope, ErrorInfo {
+				cause: "macro expansion failed",
+				pointer: msg.as_str(),
+				context: Some("macro call"),
+				help: None,
+			}).note(external.path.scope.clone(), "macro declared here")),
+		};
+		// what the macro prints to stderr is shown at the call
+		let stderr = output.stderr.trim();
+		if !output.success {
+			let pointer = if stderr.is_empty() { "the macro exited with an error" } else { stderr };
+			self.fatal(error(scope, ErrorInfo {
+				cause: "macro expansion failed",
+				pointer,
+				context: Some("macro call"),
+				help: None,
+			}).note(external.path.scope.clone(), "macro declared here"));
+		}
+		if !stderr.is_empty() {
+			warning(scope, ErrorInfo {
+				cause: "macro warning",
+				pointer: stderr,
+				context: Some("macro call"),
+				help: None,
+			}).warn();
+		}
+		for line in output.stdout.lines() {
+			if line.trim().is_empty() { continue }
+			commands.push(line.trim().to_owned());
+		}
+	}
+}
+
+struct MacroOutput {
+	success: bool,
+	stdout: String,
+	stderr: String,
+}
+
+// the path of the executable is relative to the file the macro is declared in
+fn run_macro(external:&Macro, input:&str) -> Result<MacroOutput, String> {
+	let file = external.path.scope.file.path();
+	let dir = match Path::new(file).parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+		_ => Path::new(".").to_path_buf(),
+	};
+	let path = dir.join(&external.path.value);
+	let mut child = Command::new(&path)
+		.arg(input)
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|err| format!("couldn't run `{}`: {}", path.display(), err))?;
+
+	// the output is read while the macro runs, so it can't fill up the pipes and block
+	let mut stdout = child.stdout.take().unwrap();
+	let mut stderr = child.stderr.take().unwrap();
+	let stdout = thread::spawn(move || {
+		let mut text = String::new();
+		stdout.read_to_string(&mut text).map(|_| text)
+	});
+	let stderr = thread::spawn(move || {
+		let mut text = String::new();
+		stderr.read_to_string(&mut text).map(|_| text)
+	});
+
+	let start = Instant::now();
+	let status = loop {
+		match child.try_wait() {
+			Ok(Some(status)) => break status,
+			Ok(None) if start.elapsed() > MACRO_TIMEOUT => {
+				// the macro is stopped either way
+				let _ = child.kill();
+				let _ = child.wait();
+				return Err(format!("the macro didn't finish within {} seconds", MACRO_TIMEOUT.as_secs()));
+			}
+			Ok(None) => thread::sleep(Duration::from_millis(10)),
+			Err(err) => return Err(format!("couldn't wait for the macro: {}", err)),
+		}
+	};
+	let read = |output:thread::JoinHandle<std::io::Result<String>>| {
+		output.join().unwrap().map_err(|_| "the macro printed invalid UTF-8".to_owned())
+	};
+	Ok(MacroOutput {
+		success: status.success(),
+		stdout: read(stdout)?,
+		stderr: read(stderr)?,
+	})
+}
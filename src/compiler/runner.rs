@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use crate::compiler::{Compiler, OutFile};
+use crate::lexer::files::SourceMap;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+// compiles a datapack written in one file
+pub fn compile(source:&str) -> Vec<OutFile> {
+	let mut sources = SourceMap::new();
+	let file = sources.add("test.mccs", source);
+	let tokens = Lexer::new(file).lex();
+	let packs = [Parser::new(tokens).parse()];
+	Compiler::new(&packs[0], &packs).compile()
+}
+
+// runs the functions of a compiled datapack, with only the commands the compiler generates.
+// every selector is the one entity running the function, and a command that isn't understood fails the test
+pub struct Runner {
+	functions: HashMap<String, Vec<String>>,
+	scores: HashMap<(String, String), i32>,
+	frames: Vec<HashMap<String, i32>>,
+	// the functions scheduled for the next tick
+	scheduled: Vec<String>,
+	// the messages of `say` commands
+	pub said: Vec<String>,
+	// the commands run so far, so a loop that never ends stops the test
+	run: usize,
+}
+impl Runner {
+	pub fn new(source:&str) -> Self {
+		let mut functions = HashMap::new();
+		for file in compile(source) {
+			let Some(path) = file.path.strip_prefix("data/") else { continue };
+			let Some((namespace, path)) = path.split_once("/functions/") else { continue };
+			let name = format!("{}:{}", namespace, path.trim_end_matches(".mcfunction"));
+			functions.insert(name, file.contents.lines().map(str::to_owned).collect());
+		}
+		Runner { functions, scores: HashMap::new(), frames: Vec::new(), scheduled: Vec::new(), said: Vec::new(), run: 0 }
+	}
+
+	pub fn get(&self, holder:&str, objective:&str) -> i32 {
+		self.scores.get(&(holder.to_owned(), objective.to_owned())).copied().unwrap_or(0)
+	}
+	pub fn set(&mut self, holder:&str, objective:&str, value:i32) {
+		self.scores.insert((holder.to_owned(), objective.to_owned()), value);
+	}
+
+	pub fn call(&mut self, function:&str) {
+		let commands = self.functions.get(function)
+			.unwrap_or_else(|| panic!("unknown function {}", function))
+			.clone();
+		for command in commands {
+			if command.starts_with('#') { continue }
+			self.command(command.split_whitespace().collect::<Vec<_>>().as_slice());
+		}
+	}
+	// runs the functions scheduled in the last tick, and returns whether there were any
+	pub fn tick(&mut self) -> bool {
+		let scheduled = std::mem::take(&mut self.scheduled);
+		for function in &scheduled {
+			self.call(function);
+		}
+		!scheduled.is_empty()
+	}
+	pub fn is_scheduled(&self) -> bool {
+		!self.scheduled.is_empty()
+	}
+
+	// runs a command, and returns its result if it succeeds
+	fn command(&mut self, command:&[&str]) -> Option<i32> {
+		self.run += 1;
+		assert!(self.run < 1_000_000, "too many commands run");
+		match command {
+			["function", function] => {
+				self.call(function);
+				Some(1)
+			}
+			["schedule", "function", function, "1t", "append"] => {
+				self.scheduled.push(function.to_string());
+				Some(1)
+			}
+			["say", message @ ..] => {
+				self.said.push(message.join(" "));
+				Some(1)
+			}
+			["scoreboard", "objectives", "add", ..] => Some(1),
+			["scoreboard", "players", "get", holder, objective] => Some(self.get(holder, objective)),
+			["scoreboard", "players", "set", holder, objective, value] => {
+				self.set(holder, objective, value.parse().unwrap());
+				Some(1)
+			}
+			["scoreboard", "players", "add", holder, objective, value] => {
+				self.set(holder, objective, self.get(holder, objective).wrapping_add(value.parse().unwrap()));
+				Some(1)
+			}
+			["scoreboard", "players", "remove", holder, objective, value] => {
+				self.set(holder, objective, self.get(holder, objective).wrapping_sub(value.parse().unwrap()));
+				Some(1)
+			}
+			["scoreboard", "players", "operation", holder, objective, op, source, source_objective] => {
+				let (a, b) = (self.get(holder, objective), self.get(source, source_objective));
+				let value = match *op {
+					"=" => b,
+					"+=" => a.wrapping_add(b),
+					"-=" => a.wrapping_sub(b),
+					"*=" => a.wrapping_mul(b),
+					"/=" if b == 0 => return None,
+					"/=" => floor_div(a, b),
+					"%=" if b == 0 => return None,
+					"%=" => a.wrapping_sub(floor_div(a, b).wrapping_mul(b)),
+					"<" => a.min(b),
+					">" => a.max(b),
+					"><" => {
+						self.set(source, source_objective, a);
+						b
+					}
+					_ => panic!("unknown operation {}", op),
+				};
+				self.set(holder, objective, value);
+				Some(1)
+			}
+			["data", "modify", "storage", _, "frames", "append", "value", "{}"] => {
+				self.frames.push(HashMap::new());
+				Some(1)
+			}
+			["data", "remove", "storage", _, "frames[-1]"] => {
+				self.frames.pop().expect("no frame to remove");
+				Some(1)
+			}
+			["data", "get", "storage", _, path] => {
+				let slot = path.strip_prefix("frames[-1].").unwrap();
+				self.frames.last().expect("no frame to read").get(slot).copied()
+			}
+			["execute", rest @ ..] => self.execute(rest),
+			_ => panic!("unknown command {}", command.join(" ")),
+		}
+	}
+
+	fn execute(&mut self, mut args:&[&str]) -> Option<i32> {
+		let mut store = None;
+		loop {
+			match args {
+				["run", command @ ..] => {
+					let result = self.command(command);
+					if let Some((success, holder, objective)) = store {
+						let value = match (success, result) {
+							(true, result) => result.is_some() as i32,
+							(false, result) => result.unwrap_or(0),
+						};
+						self.set(holder, objective, value);
+					}
+					return result;
+				}
+				["store", kind @ ("result" | "success"), "score", holder, objective, rest @ ..] => {
+					store = Some((*kind == "success", *holder, *objective));
+					args = rest;
+				}
+				["store", "result", "storage", _, path, "int", "1", rest @ ..] => {
+					let slot = path.strip_prefix("frames[-1].").unwrap();
+					let result = self.execute(rest);
+					let frame = self.frames.last_mut().expect("no frame to store into");
+					frame.insert(slot.to_owned(), result.unwrap_or(0));
+					return result;
+				}
+				[kind @ ("if" | "unless"), rest @ ..] => {
+					let (holds, rest) = self.condition(rest);
+					if holds != (*kind == "if") {
+						if let (Some((_, holder, objective)), []) = (store, rest) {
+							self.set(holder, objective, 0);
+						}
+						return None;
+					}
+					if rest.is_empty() {
+						if let Some((_, holder, objective)) = store {
+							self.set(holder, objective, 1);
+						}
+						return Some(1);
+					}
+					args = rest;
+				}
+				["as" | "at", "@s", rest @ ..] => args = rest,
+				_ => panic!("unknown execute arguments {}", args.join(" ")),
+			}
+		}
+	}
+
+	fn condition<'c, 's>(&self, args:&'c [&'s str]) -> (bool, &'c [&'s str]) {
+		match args {
+			["entity", _, rest @ ..] => (true, rest),
+			["score", holder, objective, "matches", range, rest @ ..] => {
+				let value = self.get(holder, objective);
+				let (min, max) = match range.split_once("..") {
+					Some((min, max)) => (min.parse().unwrap_or(i32::MIN), max.parse().unwrap_or(i32::MAX)),
+					None => (range.parse().unwrap(), range.parse().unwrap()),
+				};
+				(min <= value && value <= max, rest)
+			}
+			["score", holder, objective, op, source, source_objective, rest @ ..] => {
+				let (a, b) = (self.get(holder, objective), self.get(source, source_objective));
+				let holds = match *op {
+					"<" => a < b,
+					"<=" => a <= b,
+					"=" => a == b,
+					">=" => a >= b,
+					">" => a > b,
+					_ => panic!("unknown comparison {}", op),
+				};
+				(holds, rest)
+			}
+			_ => panic!("unknown condition {}", args.join(" ")),
+		}
+	}
+}
+
+// scoreboard division rounds down
+fn floor_div(a:i32, b:i32) -> i32 {
+	let quotient = a.wrapping_div(b);
+	if a % b != 0 && (a < 0) != (b < 0) { quotient - 1 } else { quotient }
+}
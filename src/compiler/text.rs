@@ -0,0 +1,398 @@
+use crate::compiler::Compiler;
+use crate::compiler::frame::{Frame, Score, Value};
+use crate::debug::errors::{error, ErrorInfo, Help};
+use crate::lexer::tokens::{Ident, SrcScope, Str, Token};
+use crate::parser::{Arg, MacroCall};
+
+// the macros that are part of the language, they can be replaced by an external macro with the same name
+pub const BUILTIN_MACROS:[&str; 2] = ["print", "tellraw"];
+
+const COLORS:[&str; 16] = [
+	"black", "dark_blue", "dark_green", "dark_aqua", "dark_red", "dark_purple", "gold", "gray",
+	"dark_gray", "blue", "green", "aqua", "red", "light_purple", "yellow", "white",
+];
+const FORMATS:[&str; 5] = ["bold", "italic", "underlined", "strikethrough", "obfuscated"];
+
+#[derive(Clone, Default, PartialEq)]
+struct Style {
+	color: Option<String>,
+	formats: Vec<&'static str>,
+}
+impl Style {
+	fn json(&self) -> String {
+		let mut json = String::new();
+		if let Some(color) = &self.color {
+			json.push_str(format!(",\"color\":\"{}\"", color).as_str());
+		}
+		for format in &self.formats {
+			json.push_str(format!(",\"{}\":true", format).as_str());
+		}
+		json
+	}
+}
+
+enum Content {
+	Text(String),
+	Score(Score),
+	Selector(String),
+	// the path, and what it's read from, `storage`, `entity` or `block`, with its location
+	Nbt(String, &'static str, String),
+}
+
+struct Component {
+	content: Content,
+	style: Style,
+}
+impl Component {
+	fn json(&self) -> String {
+		let style = self.style.json();
+		match &self.content {
+			Content::Text(text) if style.is_empty() => json_string(text),
+			Content::Text(text) => format!("{{\"text\":{}{}}}", json_string(text), style),
+			Content::Score(score) => format!(
+				"{{\"score\":{{\"name\":{},\"objective\":{}}}{}}}",
+				json_string(&score.holder), json_string(&score.objective), style
+			),
+			Content::Selector(selector) => format!("{{\"selector\":{}{}}}", json_string(selector), style),
+			Content::Nbt(path, source, location) => format!(
+				"{{\"nbt\":{},\"{}\":{}{}}}",
+				json_string(path), source, json_string(location), style
+			),
+		}
+	}
+}
+
+impl<'a> Compiler<'a> {
+	// `print!("...")` shows the text to every player, `tellraw!(target, "...")` only to the target.
+	// `{...}` in the text is a score, a selector, NBT or a style for the text after it
+	pub(super) fn compile_tellraw(&self, frame:&Frame, call:&MacroCall, commands:&mut Vec<String>) {
+		let tokens = &call.group.tokens[..call.group.tokens.len()-1];
+		let (target, text) = match (call.name.value.as_str(), tokens) {
+			("print", [Token::Str(text)]) => ("@a".to_owned(), text),
+			("tellraw", [target @ .., Token::Sep(sep), Token::Str(text)]) if sep.value == ',' && !target.is_empty() => {
+				let arg = Arg { tokens: target.to_vec(), sep: None };
+				let Value::Target(selector) = self.arg_value(frame, &arg, "macro call") else {
+					self.fatal(error(arg.scope(), ErrorInfo {
+						cause: "invalid target",
+						pointer: "expected a target selector",
+						context: Some("macro call"),
+						help: None,
+					}))
+				};
+				(selector.to_string(), text)
+			}
+			(name, _) => self.fatal(error(call.group.scope.clone(), ErrorInfo {
+				cause: "invalid macro input",
+				pointer: "expected the text to show as a string",
+				context: Some("macro call"),
+				help: Some(Help {
+					msg: "the text can show scores, selectors and NBT".to_owned(),
+					source: match name {
+						"print" => "print!(\"you have {score:@s tokens} tokens\");",
+						_ => "tellraw!(@s, \"you have {score:@s tokens} tokens\");",
+					}.to_owned(),
+					line: 0,
+				}),
+			})),
+		};
+		let components = self.text_components(frame, text);
+		let mut json:Vec<String> = components.iter().map(Component::json).collect();
+		// every part of the text gets the style of the first one, unless it's set
+		if components.first().is_some_and(|first| first.style != Style::default()) {
+			json.insert(0, "\"\"".to_owned());
+		}
+		commands.push(format!("tellraw {} [{}]", target, json.join(",")));
+	}
+
+	fn text_components(&self, frame:&Frame, text:&Str) -> Vec<Component> {
+		let chars:Vec<char> = text.value.chars().collect();
+		let mut components:Vec<Component> = Vec::new();
+		let mut style = Style::default();
+		let mut plain = String::new();
+		let mut i = 0;
+		while i < chars.len() {
+			let c = chars[i];
+			// `{{` and `}}` are braces in the text
+			if (c == '{' || c == '}') && chars.get(i+1) == Some(&c) {
+				plain.push(c);
+				i += 2;
+				continue;
+			}
+			if c == '}' {
+				self.fatal(error(text.value_scope(i, i+1), ErrorInfo {
+					cause: "invalid placeholder",
+					pointer: "this `}` doesn't close a placeholder",
+					context: Some("text"),
+					help: Some(Help {
+						msg: "braces in the text are written twice".to_owned(),
+						source: "print!(\"{{not a placeholder}}\");".to_owned(),
+						line: 0,
+					}),
+				}));
+			}
+			if c != '{' {
+				plain.push(c);
+				i += 1;
+				continue;
+			}
+			let mut nesting = Nesting::default();
+			let Some(len) = chars[i+1..].iter().position(|c| nesting.outside(*c) && *c == '}') else {
+				self.fatal(error(text.value_scope(i, i+1), ErrorInfo {
+					cause: "invalid placeholder",
+					pointer: "this placeholder is never closed",
+					context: Some("text"),
+					help: None,
+				}))
+			};
+			let end = i+1+len;
+			if !plain.is_empty() {
+				push_text(&mut components, std::mem::take(&mut plain), &style);
+			}
+			let placeholder = Placeholder { text, start: i+1, end };
+			if let Some(content) = self.placeholder(frame, &placeholder, &chars, &mut style) {
+				components.push(Component { content, style: style.clone() });
+			}
+			i = end+1;
+		}
+		if !plain.is_empty() {
+			push_text(&mut components, plain, &style);
+		}
+		components
+	}
+
+	// a value to show, or nothing if the placeholder changes the style
+	fn placeholder(&self, frame:&Frame, placeholder:&Placeholder, chars:&[char], style:&mut Style) -> Option<Content> {
+		let words = placeholder.words(chars);
+		let Some((first, first_start)) = words.first().cloned() else {
+			self.fatal(error(placeholder.scope(placeholder.start-1, placeholder.end+1), ErrorInfo {
+				cause: "invalid placeholder",
+				pointer: "this placeholder is empty",
+				context: Some("text"),
+				help: None,
+			}))
+		};
+		let Some((kind, value)) = first.split_once(':') else {
+			self.set_style(placeholder, &words, style);
+			return None;
+		};
+		// the words after the kind, the first one right after the colon
+		let mut args = words.clone();
+		let value_start = first_start+kind.chars().count()+1;
+		args[0] = (value.to_owned(), value_start);
+		if value.is_empty() {
+			args.remove(0);
+		}
+		let content = match kind {
+			"score" => Content::Score(self.placeholder_score(frame, placeholder, &args)),
+			"selector" => {
+				let [(selector, start)] = args.as_slice() else {
+					self.invalid_args(placeholder, &args, "expected a single selector, like `{selector:@s}`")
+				};
+				let text = self.substituted(placeholder, selector, *start);
+				if !is_selector(&text) {
+					self.invalid_word(placeholder, selector, *start, "expected a selector, like `@s` or `@e[type=cow]`");
+				}
+				Content::Selector(text)
+			}
+			"nbt" => {
+				let [(path, path_start), source @ ..] = args.as_slice() else {
+					self.invalid_args(placeholder, &args, "expected an NBT path and what it's read from")
+				};
+				let path = self.substituted(placeholder, path, *path_start);
+				let location:Vec<String> = source.iter()
+					.map(|(word, start)| self.substituted(placeholder, word, *start))
+					.collect();
+				match location.as_slice() {
+					[selector] if is_selector(selector) => Content::Nbt(path, "entity", selector.clone()),
+					[storage] if storage.contains(':') => Content::Nbt(path, "storage", storage.clone()),
+					[_, _, _] => Content::Nbt(path, "block", location.join(" ")),
+					_ => self.invalid_args(
+						placeholder, source,
+						"expected a selector, a storage like `ns:name`, or the coordinates of a block",
+					),
+				}
+			}
+			_ => self.invalid_word(placeholder, kind, first_start, "expected `score`, `selector` or `nbt` before the colon"),
+		};
+		Some(content)
+	}
+
+	// `{score:holder objective}`, or `{score:name}` for a local, param or pipe
+	fn placeholder_score(&self, frame:&Frame, placeholder:&Placeholder, args:&[(String, usize)]) -> Score {
+		match args {
+			[(holder, holder_start), (objective, objective_start)] => Score::new(
+				self.substituted(placeholder, holder, *holder_start).as_str(),
+				self.substituted(placeholder, objective, *objective_start).as_str(),
+			),
+			[(name, start)] => {
+				let scope = placeholder.scope(*start, start+name.chars().count());
+				let score = if let Some(arg) = name.strip_prefix('$') {
+					match self.inline_arg(arg, scope) {
+						Value::Score(score) => Some(score),
+						_ => None,
+					}
+				} else if let Some(pipe) = name.strip_prefix('|') {
+					frame.pipe(&Ident { value: pipe.to_owned(), scope })
+				} else {
+					frame.local(name).or_else(|| frame.param(name))
+				};
+				score.unwrap_or_else(|| self.invalid_word(
+					placeholder, name, *start,
+					"expected a local, a param, a pipe or a score, or a holder and an objective",
+				))
+			}
+			_ => self.invalid_args(placeholder, args, "expected a holder and an objective, like `{score:@s tokens}`"),
+		}
+	}
+
+	// `{red}`, `{bold italic}`, `{#ff8000}` and `{reset}` change the style of the text after them
+	fn set_style(&self, placeholder:&Placeholder, words:&[(String, usize)], style:&mut Style) {
+		for (word, start) in words {
+			let word = word.as_str();
+			if word == "reset" {
+				*style = Style::default();
+			} else if COLORS.contains(&word) || is_hex_color(word) {
+				style.color = Some(word.to_owned());
+			} else if let Some(format) = FORMATS.iter().find(|format| **format == word) {
+				if !style.formats.contains(format) {
+					style.formats.push(format);
+				}
+			} else {
+				self.invalid_word(
+					placeholder, word, *start,
+					"expected `score:`, `selector:`, `nbt:`, a color, a format like `bold`, or `reset`",
+				);
+			}
+		}
+	}
+
+	// `$name` is replaced with the argument of the inline being expanded
+	fn substituted(&self, placeholder:&Placeholder, word:&str, start:usize) -> String {
+		let Some(name) = word.strip_prefix('$') else { return word.to_owned() };
+		let scope = placeholder.scope(start, start+word.chars().count());
+		self.inline_arg(name, scope).text()
+	}
+
+	fn invalid_word(&self, placeholder:&Placeholder, word:&str, start:usize, pointer:&str) -> ! {
+		self.fatal(error(placeholder.scope(start, start+word.chars().count()), ErrorInfo {
+			cause: "invalid placeholder",
+			pointer,
+			context: Some("text"),
+			help: None,
+		}))
+	}
+	fn invalid_args(&self, placeholder:&Placeholder, args:&[(String, usize)], pointer:&str) -> ! {
+		let scope = match (args.first(), args.last()) {
+			(Some((_, start)), Some((last, last_start))) => {
+				placeholder.scope(*start, last_start+last.chars().count())
+			}
+			_ => placeholder.scope(placeholder.start-1, placeholder.end+1),
+		};
+		self.fatal(error(scope, ErrorInfo {
+			cause: "invalid placeholder",
+			pointer,
+			context: Some("text"),
+			help: None,
+		}))
+	}
+}
+
+// the chars `start..end` of a text are between the braces of a placeholder
+struct Placeholder<'t> {
+	text: &'t Str,
+	start: usize,
+	end: usize,
+}
+impl Placeholder<'_> {
+	// the words in the placeholder, with the index of their first char in the text
+	fn words(&self, chars:&[char]) -> Vec<(String, usize)> {
+		let mut words:Vec<(String, usize)> = Vec::new();
+		let mut in_word = false;
+		let mut nesting = Nesting::default();
+		for (i, c) in chars.iter().enumerate().take(self.end).skip(self.start) {
+			if nesting.outside(*c) && c.is_whitespace() {
+				in_word = false;
+				continue;
+			}
+			if !in_word {
+				words.push((String::new(), i));
+				in_word = true;
+			}
+			words.last_mut().unwrap().0.push(*c);
+		}
+		words
+	}
+	fn scope(&self, start:usize, end:usize) -> SrcScope {
+		self.text.value_scope(start, end)
+	}
+}
+
+// NBT in a placeholder can have braces, brackets and quoted strings,
+// the `}` and spaces in them don't end the placeholder or the word
+#[derive(Default)]
+struct Nesting {
+	depth: usize,
+	quote: Option<char>,
+	escaped: bool,
+}
+impl Nesting {
+	// whether `c` is outside of every bracket and string, given the chars before it
+	fn outside(&mut self, c:char) -> bool {
+		if let Some(quote) = self.quote {
+			if self.escaped {
+				self.escaped = false;
+			} else if c == '\\' {
+				self.escaped = true;
+			} else if c == quote {
+				self.quote = None;
+			}
+			return false;
+		}
+		match c {
+			'"' | '\'' => self.quote = Some(c),
+			'{' | '[' => self.depth += 1,
+			'}' | ']' if self.depth > 0 => self.depth -= 1,
+			_ => return self.depth == 0,
+		}
+		false
+	}
+}
+
+// text next to text with the same style is merged into it
+fn push_text(components:&mut Vec<Component>, text:String, style:&Style) {
+	if let Some(Component { content: Content::Text(last), style: last_style }) = components.last_mut() {
+		if last_style == style {
+			last.push_str(text.as_str());
+			return;
+		}
+	}
+	components.push(Component { content: Content::Text(text), style: style.clone() });
+}
+
+fn is_selector(text:&str) -> bool {
+	let mut chars = text.chars();
+	chars.next() == Some('@') && chars.next().is_some_and(|base| "aenprs".contains(base))
+		&& (text.len() == 2 || text[2..].starts_with('[') && text.ends_with(']'))
+}
+
+fn is_hex_color(text:&str) -> bool {
+	text.len() == 7 && text.starts_with('#') && text[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// a JSON string with the text, escaped so it can be put in a command
+pub fn json_string(text:&str) -> String {
+	let mut json = String::from("\"");
+	for c in text.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			'\n' => json.push_str("\\n"),
+			'\t' => json.push_str("\\t"),
+			'\r' => json.push_str("\\r"),
+			c if c.is_control() => json.push_str(format!("\\u{:04x}", c as u32).as_str()),
+			c => json.push(c),
+		}
+	}
+	json.push('"');
+	json
+}
@@ -1,6 +1,5 @@
 use std::fmt::{Debug, Formatter};
-use crate::parser::{Datapack, FunArgs, Function, Inline, InlineArg, Scoreboard};
-use crate::compiler::tokens::{Group, Token};
+use crate::parser::{AtBlock, Call, For, If, While, Datapack, FunArgs, Function, Inline, InlineArg, Scoreboard, Statement, Unsafe, UnsafeCmd};
 use crate::lexer::tokens::{Group, Token};
 
 pub mod bash_tools;
@@ -28,23 +27,27 @@ impl NewDebugTree for Function {
 	fn debug_tree(&self) -> DebugTree {
 		DebugTree::new("Function")
 			.node_debug("name", &self.name)
+			.list_debug("docs", &self.docs)
 			.node("args", &self.args)
 			.node_debug("at", &self.at)
-			.node("block", &self.block)
+			.list("body", &self.body)
 	}
 }
 impl NewDebugTree for Scoreboard {
 	fn debug_tree(&self) -> DebugTree {
 		DebugTree::new("Scoreboard")
 			.node_debug("name", &self.name)
+			.list_debug("docs", &self.docs)
 			.node_debug("objective", &self.objective)
 	}
 }
 impl NewDebugTree for Inline {
 	fn debug_tree(&self) -> DebugTree {
 		DebugTree::new("Inline")
+			.node_debug("name", &self.name)
+			.list_debug("docs", &self.docs)
 			.list("args", &self.args)
-			.node("block", &self.block)
+			.list("body", &self.body)
 	}
 }
 impl NewDebugTree for InlineArg {
@@ -54,6 +57,72 @@ impl NewDebugTree for InlineArg {
 			.node_debug("type", &self.ty)
 	}
 }
+impl NewDebugTree for Statement {
+	fn debug_tree(&self) -> DebugTree {
+		match self {
+			Self::Unsafe(e) => e.debug_tree(),
+			Self::Scoreboard(e) => e.debug_tree(),
+			Self::Call(e) => e.debug_tree(),
+			Self::At(e) => e.debug_tree(),
+			Self::If(e) => e.debug_tree(),
+			Self::While(e) => e.debug_tree(),
+			Self::For(e) => e.debug_tree(),
+			_ => DebugTree::new(format!("{:?}", self).as_str()),
+		}
+	}
+}
+impl NewDebugTree for If {
+	fn debug_tree(&self) -> DebugTree {
+		let tree = DebugTree::new("If")
+			.node_debug("condition", &self.condition)
+			.list("body", &self.body);
+		match &self.otherwise {
+			Some(otherwise) => tree.list("else", &otherwise.body),
+			None => tree,
+		}
+	}
+}
+impl NewDebugTree for While {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new("While")
+			.node_debug("condition", &self.condition)
+			.list("body", &self.body)
+	}
+}
+impl NewDebugTree for For {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new("For")
+			.node_debug("var", &self.var)
+			.node_debug("start", &self.start)
+			.node_debug("end", &self.end)
+			.list("body", &self.body)
+	}
+}
+impl NewDebugTree for AtBlock {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new("At")
+			.list("target", &self.target.tokens)
+			.list("body", &self.body)
+	}
+}
+impl NewDebugTree for Unsafe {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new("Unsafe")
+			.list("commands", &self.commands)
+	}
+}
+impl NewDebugTree for UnsafeCmd {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new(format!("{:?}", self.raw).as_str())
+	}
+}
+impl NewDebugTree for Call {
+	fn debug_tree(&self) -> DebugTree {
+		DebugTree::new("Call")
+			.node_debug("path", &self.path)
+			.list_debug("args", &self.args)
+	}
+}
 impl NewDebugTree for FunArgs {
 	fn debug_tree(&self) -> DebugTree {
 		DebugTree::new("FunArgs")
@@ -136,7 +205,7 @@ impl DebugTree {
 	}
 
 	fn fmt_indent(&self, f:&mut Formatter<'_>, indent:usize) -> std::fmt::Result {
-		const INDENT:&'static str = " ";
+		const INDENT:&str = " ";
 		f.write_str(INDENT.repeat(indent).as_str())?;
 		if let Some(field) = &self.field {
 			f.write_str(field.as_str())?;
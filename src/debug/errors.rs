@@ -1,5 +1,5 @@
 use std::fmt::Debug;
-use crate::compiler::tokens::SrcScope;
+use crate::lexer::tokens::SrcScope;
 use crate::debug::bash_tools::*;
 
 pub struct ErrorInfo<'a, 'b, 'c> {
@@ -15,22 +15,59 @@ pub struct Help {
 	pub line: usize,
 }
 
+// the message, and the scope it points at
 #[derive(Debug)]
 pub enum CompileErr {
-	Error(String),
-	Warning(String),
+	Error(String, SrcScope),
+	Warning(String, SrcScope),
 }
 impl CompileErr {
 	pub fn msg(self) -> String {
 		match self {
-			Self::Error(msg) => msg,
-			Self::Warning(msg) => msg,
+			Self::Error(msg, _) => msg,
+			Self::Warning(msg, _) => msg,
+		}
+	}
+	pub fn scope(&self) -> &SrcScope {
+		match self {
+			Self::Error(_, scope) => scope,
+			Self::Warning(_, scope) => scope,
 		}
 	}
 	pub fn fatal(self) -> ! {
+		#[cfg(test)]
+		FATAL_SCOPE.with(|last| *last.borrow_mut() = Some(self.scope().clone()));
 		println!("{}", self.msg());
 		panic!("fatal compiling error");
 	}
+	// prints the message and carries on compiling
+	pub fn warn(self) {
+		println!("{}", self.msg());
+	}
+
+	// points at another part of the source that is related to this error
+	pub fn note(self, scope:SrcScope, msg:&str) -> Self {
+		let mut fmt = DebugLines::new();
+		fmt.push_line(DebugLine::Text(format!(
+			"{}note: {}{}{}",
+			Fmt::DecorColor(BOLD, CYAN),
+			Fmt::DecorColor(BOLD, LIGHT_WHITE),
+			msg,
+			Fmt::Reset,
+		)));
+		underline_scope(
+			&mut fmt,
+			scope,
+			"",
+			Fmt::DecorColor(PLAIN, WHITE),
+			Fmt::DecorColor(BOLD, CYAN),
+		);
+		let note = fmt.fmt();
+		match self {
+			Self::Error(msg, scope) => Self::Error(msg + note.as_str(), scope),
+			Self::Warning(msg, scope) => Self::Warning(msg + note.as_str(), scope),
+		}
+	}
 }
 
 #[derive(Debug)]
@@ -55,15 +92,31 @@ pub fn warning(scope:SrcScope, info:ErrorInfo) -> CompileErr {
 	CompileErr::Warning(create_error(
 		Fmt::DecorColor(BOLD,YELLOW),
 		"warning",
-		scope, info
-	))
+		scope.clone(), info
+	), scope)
 }
 pub fn error(scope:SrcScope, info:ErrorInfo) -> CompileErr {
 	CompileErr::Error(create_error(
 		Fmt::DecorColor(BOLD,RED),
 		"error",
-		scope, info
-	))
+		scope.clone(), info
+	), scope)
+}
+
+#[cfg(test)]
+thread_local! {
+	// where the last fatal error on this thread points, so tests can check it
+	static FATAL_SCOPE: std::cell::RefCell<Option<SrcScope>> = const { std::cell::RefCell::new(None) };
+}
+
+// runs `f`, which must stop with a fatal error, and returns the start and end of its scope
+#[cfg(test)]
+pub fn fatal_scope<T>(f:impl FnOnce() -> T) -> (usize, usize) {
+	FATAL_SCOPE.with(|last| *last.borrow_mut() = None);
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+	assert!(result.is_err(), "expected a fatal error");
+	let scope = FATAL_SCOPE.with(|last| last.borrow_mut().take()).expect("expected a fatal error");
+	(scope.start, scope.end)
 }
 
 fn create_error(
@@ -114,7 +167,7 @@ fn underline_scope(
 	let first_line_pos = scope.file.get_line_pos(lines[0]);
 
 	fmt.push_line(DebugLine::File {
-		path: scope.file.path().clone(),
+		path: scope.file.path().to_owned(),
 		line: lines[0],
 		col: scope.start-first_line_pos,
 	});
@@ -135,10 +188,10 @@ fn underline_scope(
 		});
 	}
 	else {
-		for i in 0..lines.len() {
+		for line in &lines {
 			fmt.push_line(DebugLine::GroupedSource {
-				line: lines[i],
-				source: scope.file.line_source(lines[i]),
+				line: *line,
+				source: scope.file.line_source(*line),
 				color: source_color,
 				group_color: underline_color,
 			});
@@ -229,7 +282,7 @@ impl DebugLine {
 				string.push_str(color.to_string().as_str());
 				string.push_str(" ".repeat(*offset).as_str());
 				string.push_str(ch.to_string().repeat(*len).as_str());
-				string.push_str(" ");
+				string.push(' ');
 				string.push_str(msg.as_str());
 				string.push_str(Fmt::Reset.to_string().as_str());
 				string
@@ -244,7 +297,7 @@ impl DebugLine {
 				string.push_str(color.to_string().as_str());
 				string.push_str(" ".repeat(*offset).as_str());
 				string.push_str(ch.to_string().repeat(*len).as_str());
-				string.push_str(" ");
+				string.push(' ');
 				string.push_str(msg.as_str());
 				string.push_str(Fmt::Reset.to_string().as_str());
 				string
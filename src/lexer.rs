@@ -1,8 +1,9 @@
 use files::FileRef;
-use crate::debug::errors::{error, ErrorInfo};
+use crate::debug::errors::{error, ErrorInfo, Help};
 use crate::lexer::tokens::{Delimiter, SrcScope, Token};
 
 pub mod files;
+pub mod selector;
 pub mod tokens;
 pub mod token_stream;
 
@@ -10,6 +11,12 @@ pub struct Lexer {
 	file: FileRef,
 	chars: Vec<char>,
 	index: usize,
+	// the last token was `unsafe`, so the next block is lexed as raw commands
+	raw_block: bool,
+	// the closing delimiters of the groups being lexed, innermost last
+	groups: Vec<char>,
+	// the last token ends a value, so a `-` after it is a minus instead of the sign of a number
+	after_value: bool,
 }
 
 impl Lexer {
@@ -19,6 +26,9 @@ impl Lexer {
 			file,
 			chars,
 			index: 0,
+			raw_block: false,
+			groups: Vec::new(),
+			after_value: false,
 		}
 	}
 
@@ -29,6 +39,7 @@ impl Lexer {
 		while self.index < num_chars {
 			let c = self.chars[self.index];
 			if let Some(token) = self.next(c) {
+				self.after_value = ends_value(&token);
 				tokens.push(token);
 			}
 		};
@@ -38,30 +49,28 @@ impl Lexer {
 
 	fn next(&mut self, c:char) -> Option<Token> {
 		let num_chars = self.chars.len();
-		if c == '"' {
-			let opening_pos = self.index;
-			let mut string = String::new();
-			let mut found_closing = false;
+		if c.is_whitespace() {
 			self.index += 1;
-			while self.index < num_chars {
-				let c = self.chars[self.index];
-				self.index += 1;
-				if c == '"' {
-					found_closing = true;
-					break;
-				}
-				string.push(c);
-			}
-			let scope = self.scope(opening_pos, self.index);
-			if !found_closing {
-				error(scope, ErrorInfo {
-					cause: "missing closing quote",
-					pointer: "closing quote not found",
-					context: Some("string"),
-					help: None,
-				}).fatal();
-			}
-			return Some(Token::string(string, scope));
+			return None;
+		}
+		if c == '/' && self.chars.get(self.index+1) == Some(&'/') {
+			return self.line_comment();
+		}
+		if c == '/' && self.chars.get(self.index+1) == Some(&'*') {
+			self.block_comment();
+			return None;
+		}
+		if std::mem::take(&mut self.raw_block) && c == '{' {
+			return Some(self.raw_group());
+		}
+		if c == '"' {
+			return Some(self.string());
+		}
+		if let Some(hashes) = self.raw_string_start() {
+			return Some(self.raw_string(hashes));
+		}
+		if c == '@' {
+			return Some(self.selector());
 		}
 		if is_sep(c) {
 			self.index += 1;
@@ -70,41 +79,22 @@ impl Lexer {
 			);
 			return Some(token);
 		}
+		let negative = c == '-' && !self.after_value
+			&& self.chars.get(self.index+1).is_some_and(|nc| starts_num(*nc));
+		if starts_num(c) || negative {
+			return Some(self.number());
+		}
 		if is_symbol(c) {
-			let mut value = String::new();
-			value.push(c);
-			self.index += 1;
-			while self.index < num_chars {
-				let nc = self.chars[self.index];
-				if !is_symbol(nc) { break }
-				value.push(nc);
-				self.index += 1;
-			}
+			// the longest operator that starts here, so `x=-1` is `=` and `-`
+			let next = self.chars.get(self.index+1).copied();
+			let value = match next {
+				Some(nc) if OPERATORS.contains(&[c, nc]) => [c, nc].iter().collect(),
+				_ => c.to_string(),
+			};
+			self.index += value.len();
 			let scope = self.len_scope(value.len());
 			return Some(Token::symbol(value, scope));
 		}
-		if starts_num(c) {
-			let mut value = String::new();
-			value.push(c);
-			self.index += 1;
-			while self.index < num_chars {
-				let nc = self.chars[self.index];
-				if is_ident_sep(nc) { break }
-				value.push(nc);
-				self.index += 1;
-			}
-			let scope = self.len_scope(value.len());
-			let value = match value.parse() {
-				Ok(value) => value,
-				Err(err) => error(scope, ErrorInfo {
-					cause: "invalid integer literal",
-					pointer: format!("failed to parse integer, reason: {}", err).as_str(),
-					context: Some("integer"),
-					help: None,
-				}).fatal()
-			};
-			return Some(Token::int(value, scope));
-		}
 		if starts_ident(c) {
 			let mut value = String::new();
 			value.push(c);
@@ -115,13 +105,10 @@ impl Lexer {
 				value.push(nc);
 				self.index += 1;
 			}
-			let scope = self.len_scope(value.len());
+			let scope = self.len_scope(value.chars().count());
+			self.raw_block = value == "unsafe";
 			return Some(Token::ident(value, scope));
 		}
-		if c.is_whitespace() {
-			self.index += 1;
-			return None;
-		}
 
 		if let Some(delimiter) = delimiter(c) {
 			let opening_pos = self.index;
@@ -147,11 +134,334 @@ impl Lexer {
 		}).fatal();
 	}
 
+	// an integer, which can be negative, written in hexadecimal or binary and separated with `_`,
+	// or a decimal number, optionally followed by the suffix of its NBT type
+	fn number(&mut self) -> Token {
+		let start = self.index;
+		let negative = self.chars[self.index] == '-';
+		if negative { self.index += 1 }
+		let radix = match self.chars.get(self.index+1) {
+			Some('x' | 'X') if self.chars[self.index] == '0' => 16,
+			Some('b' | 'B') if self.chars[self.index] == '0'
+				&& self.chars.get(self.index+2).is_some_and(|c| in_num(*c)) => 2,
+			_ => 10,
+		};
+		if radix != 10 { self.index += 2 }
+		let digits_start = self.index;
+		while self.chars.get(self.index).is_some_and(|c| c.is_digit(radix) || *c == '_') {
+			self.index += 1;
+		}
+		// `1..5` is a range, not a decimal
+		let decimal = radix == 10 && self.chars.get(self.index) == Some(&'.')
+			&& self.chars.get(self.index+1).is_some_and(|c| c.is_ascii_digit());
+		if decimal {
+			self.index += 1;
+			while self.chars.get(self.index).is_some_and(|c| in_num(*c)) {
+				self.index += 1;
+			}
+		}
+		let digits_end = self.index;
+		while self.chars.get(self.index).is_some_and(|c| in_ident(*c)) {
+			self.index += 1;
+		}
+		let scope = self.scope(start, self.index);
+		let suffix_scope = self.scope(digits_end, self.index);
+		let mut digits:String = self.chars[digits_start..digits_end].iter()
+			.filter(|c| **c != '_')
+			.collect();
+		if digits.is_empty() {
+			error(self.scope(start, digits_end), ErrorInfo {
+				cause: "invalid number literal",
+				pointer: "expected digits after the prefix",
+				context: Some("number"),
+				help: None,
+			}).fatal();
+		}
+		if negative { digits.insert(0, '-') }
+		let suffix:String = self.chars[digits_end..self.index].iter().collect();
+
+		let kind = match suffix.as_str() {
+			"" if decimal => "double",
+			"" => "int",
+			"b" | "B" => "byte",
+			"s" | "S" => "short",
+			"l" | "L" => "long",
+			"f" | "F" if radix == 10 => "float",
+			"d" | "D" if radix == 10 => "double",
+			_ => error(suffix_scope, ErrorInfo {
+				cause: "invalid number suffix",
+				pointer: "expected `b`, `s`, `L`, `f` or `d`",
+				context: Some("number"),
+				help: None,
+			}).fatal(),
+		};
+		let article = if kind == "int" { "an" } else { "a" };
+		if decimal && kind != "float" && kind != "double" {
+			error(scope, ErrorInfo {
+				cause: "invalid number literal",
+				pointer: format!("{} {} can't have decimals", article, kind).as_str(),
+				context: Some("number"),
+				help: None,
+			}).fatal();
+		}
+		let out_of_range = || -> ! {
+			error(scope.clone(), ErrorInfo {
+				cause: "number literal out of range",
+				pointer: format!("this number doesn't fit in {} {}", article, kind).as_str(),
+				context: Some("number"),
+				help: None,
+			}).fatal()
+		};
+		if decimal || kind == "float" || kind == "double" {
+			return match kind {
+				"float" => match digits.parse::<f32>() {
+					Ok(value) if value.is_finite() => Token::float(value, scope),
+					_ => out_of_range(),
+				},
+				_ => match digits.parse::<f64>() {
+					Ok(value) if value.is_finite() => Token::double(value, scope),
+					_ => out_of_range(),
+				},
+			}
+		}
+		let Ok(value) = i64::from_str_radix(digits.as_str(), radix) else { out_of_range() };
+		match kind {
+			"byte" => Token::byte(i8::try_from(value).unwrap_or_else(|_| out_of_range()), scope),
+			"short" => Token::short(i16::try_from(value).unwrap_or_else(|_| out_of_range()), scope),
+			"long" => Token::long(value, scope),
+			_ => Token::int(i32::try_from(value).unwrap_or_else(|_| out_of_range()), scope),
+		}
+	}
+
+	// `"..."`, or `"""..."""` which can span multiple lines
+	fn string(&mut self) -> Token {
+		let opening_pos = self.index;
+		let num_chars = self.chars.len();
+		let multiline = self.chars[self.index..].starts_with(&['"'; 3]);
+		let quotes = if multiline { 3 } else { 1 };
+		// every char of the string with where it is in the source
+		let mut chars:Vec<(char, (usize, usize))> = Vec::new();
+		let mut found_closing = false;
+		self.index += quotes;
+		while self.index < num_chars {
+			let c = self.chars[self.index];
+			if c == '"' && (!multiline || self.chars[self.index..].starts_with(&['"'; 3])) {
+				self.index += quotes;
+				found_closing = true;
+				break;
+			}
+			if c == '\\' {
+				let start = self.index;
+				let c = self.escape();
+				chars.push((c, (start, self.index)));
+				continue;
+			}
+			if c == '\n' && !multiline { break }
+			chars.push((c, (self.index, self.index+1)));
+			self.index += 1;
+		}
+		if !found_closing {
+			error(self.scope(opening_pos, opening_pos+quotes), ErrorInfo {
+				cause: "missing closing quote",
+				pointer: if multiline {
+					"this string doesn't have a matching `\"\"\"`"
+				} else {
+					"closing quote not found before the end of the line"
+				},
+				context: Some("string"),
+				help: if multiline { None } else { Some(Help {
+					msg: "strings that span multiple lines use three quotes".to_owned(),
+					source: "\"\"\"\n\tfirst line\n\tsecond line\n\t\"\"\"".to_owned(),
+					line: 0,
+				}) },
+			}).fatal();
+		}
+		if multiline {
+			chars = self.dedent(chars, opening_pos);
+		}
+		let (string, spans) = chars.into_iter().unzip();
+		Token::string(string, spans, self.scope(opening_pos, self.index))
+	}
+
+	// a multiline string starts on the line after its opening quotes, and the indentation
+	// of its closing quotes is removed from every line, so it can be indented like the code around it
+	fn dedent(&self, chars:Vec<(char, (usize, usize))>, opening_pos:usize) -> Vec<(char, (usize, usize))> {
+		let chars = match chars.first() {
+			Some(('\n', _)) => &chars[1..],
+			_ => &chars[..],
+		};
+		let Some(last_line) = chars.iter().rposition(|(c, _)| *c == '\n') else { return chars.to_vec() };
+		let indent:Vec<char> = chars[last_line+1..].iter().map(|(c, _)| *c).collect();
+		if !indent.iter().all(|c| *c == ' ' || *c == '\t') {
+			return chars.to_vec();
+		}
+		let mut dedented = Vec::new();
+		for line in chars[..last_line+1].split_inclusive(|(c, _)| *c == '\n') {
+			let text:Vec<char> = line.iter().map(|(c, _)| *c).collect();
+			if text.starts_with(&indent) {
+				dedented.extend_from_slice(&line[indent.len()..]);
+			} else if text.iter().all(|c| c.is_whitespace()) {
+				dedented.push(*line.last().unwrap());
+			} else {
+				error(self.scope(opening_pos, opening_pos+3), ErrorInfo {
+					cause: "invalid indentation",
+					pointer: "every line of this string has to be indented at least as much as its closing quotes",
+					context: Some("string"),
+					help: None,
+				}).fatal();
+			}
+		}
+		// the line break before the closing quotes isn't part of the string
+		dedented.pop();
+		dedented
+	}
+
+	// expects the current character to be the backslash
+	fn escape(&mut self) -> char {
+		self.index += 1;
+		let Some(&c) = self.chars.get(self.index) else {
+			error(self.scope(self.index-1, self.index), ErrorInfo {
+				cause: "missing closing quote",
+				pointer: "closing quote not found",
+				context: Some("string"),
+				help: None,
+			}).fatal();
+		};
+		self.index += 1;
+		match c {
+			'"' => '"',
+			'\\' => '\\',
+			'n' => '\n',
+			'u' => self.unicode_escape(),
+			_ => error(self.len_scope(1), ErrorInfo {
+				cause: "invalid escape sequence",
+				pointer: "expected `\\\"`, `\\\\`, `\\n` or `\\u{...}` after the backslash",
+				context: Some("string"),
+				help: None,
+			}).fatal(),
+		}
+	}
+
+	// `\u{...}` with the hexadecimal code of a character
+	fn unicode_escape(&mut self) -> char {
+		let invalid = |lexer:&Self, pointer:&str| -> ! {
+			let scope = lexer.scope(lexer.index, (lexer.index+1).min(lexer.chars.len()));
+			error(scope, ErrorInfo {
+				cause: "invalid unicode escape",
+				pointer,
+				context: Some("string"),
+				help: Some(Help {
+					msg: "the code of the character is written in hexadecimal".to_owned(),
+					source: "\"\\u{2764}\"".to_owned(),
+					line: 0,
+				}),
+			}).fatal()
+		};
+		if self.chars.get(self.index) != Some(&'{') {
+			invalid(self, "expected `{`");
+		}
+		self.index += 1;
+		let start = self.index;
+		while self.chars.get(self.index).is_some_and(|c| c.is_ascii_hexdigit()) {
+			self.index += 1;
+		}
+		if self.chars.get(self.index) != Some(&'}') {
+			invalid(self, "expected a hexadecimal digit or `}`");
+		}
+		let digits:String = self.chars[start..self.index].iter().collect();
+		let scope = self.scope(start, self.index);
+		self.index += 1;
+		let code = u32::from_str_radix(digits.as_str(), 16).ok().filter(|_| digits.len() <= 6);
+		match code.and_then(char::from_u32) {
+			Some(c) => c,
+			None => error(scope, ErrorInfo {
+				cause: "invalid unicode escape",
+				pointer: "this isn't the code of a unicode character",
+				context: Some("string"),
+				help: None,
+			}).fatal(),
+		}
+	}
+
+	// the number of `#` if a raw string starts here
+	fn raw_string_start(&self) -> Option<usize> {
+		if self.chars[self.index] != 'r' { return None }
+		let hashes = self.chars[self.index+1..].iter().take_while(|c| **c == '#').count();
+		if self.chars.get(self.index+1+hashes) != Some(&'"') { return None }
+		Some(hashes)
+	}
+
+	// `r#"..."#` is kept exactly as it's written, it ends at a quote followed by as many `#` as it started with
+	fn raw_string(&mut self, hashes:usize) -> Token {
+		let opening_pos = self.index;
+		let mut closing = vec!['"'];
+		closing.extend(std::iter::repeat_n('#', hashes));
+		self.index += hashes + 2;
+		let start = self.index;
+		while self.index < self.chars.len() {
+			if self.chars[self.index..].starts_with(&closing) {
+				let string = self.chars[start..self.index].iter().collect();
+				let spans = (start..self.index).map(|i| (i, i+1)).collect();
+				self.index += closing.len();
+				return Token::string(string, spans, self.scope(opening_pos, self.index));
+			}
+			self.index += 1;
+		}
+		error(self.scope(opening_pos, start), ErrorInfo {
+			cause: "missing closing quote",
+			pointer: format!("this raw string doesn't have a matching `\"{}`", "#".repeat(hashes)).as_str(),
+			context: Some("string"),
+			help: None,
+		}).fatal();
+	}
+
+	// a `///` comment is a doc comment, unless it's in parentheses or brackets where nothing can be documented
+	fn line_comment(&mut self) -> Option<Token> {
+		let start = self.index;
+		while self.index < self.chars.len() && self.chars[self.index] != '\n' {
+			self.index += 1;
+		}
+		let text:String = self.chars[start..self.index].iter().collect();
+		let doc = text.starts_with("///") && !text.starts_with("////")
+			&& self.groups.iter().all(|closing| *closing == '}');
+		if !doc { return None }
+		let value = text[3..].strip_prefix(' ').unwrap_or(&text[3..]);
+		Some(Token::doc(value.trim_end().to_owned(), self.scope(start, self.index)))
+	}
+
+	// block comments can be nested, so they can comment out code that has them
+	fn block_comment(&mut self) {
+		let start = self.index;
+		let mut depth:usize = 0;
+		while self.index < self.chars.len() {
+			let c = self.chars[self.index];
+			let next = self.chars.get(self.index+1).copied();
+			if c == '/' && next == Some('*') {
+				depth += 1;
+				self.index += 2;
+			} else if c == '*' && next == Some('/') {
+				depth -= 1;
+				self.index += 2;
+				if depth == 0 { return }
+			} else {
+				self.index += 1;
+			}
+		}
+		error(self.scope(start, start+2), ErrorInfo {
+			cause: "unterminated block comment",
+			pointer: "this comment doesn't have a matching `*/`",
+			context: Some("comment"),
+			help: None,
+		}).fatal();
+	}
+
 	fn get_group_tokens(&mut self, until:char) -> Option<Vec<Token>> {
 		let mut tokens:Vec<Token> = Vec::new();
 		let num_chars = self.chars.len();
 
 		let mut found_closing = false;
+		self.groups.push(until);
+		self.after_value = false;
 		self.index += 1;
 		while self.index < num_chars {
 			let c = self.chars[self.index];
@@ -161,22 +471,126 @@ impl Lexer {
 				break;
 			}
 			if let Some(token) = self.next(c) {
+				self.after_value = ends_value(&token);
 				tokens.push(token);
 			}
 		};
+		self.groups.pop();
 		if !found_closing { return None }
 		tokens.push(Token::end(self.len_scope(1)));
 		Some(tokens)
 	}
 
+	// the commands in an `unsafe` block are kept as they are written,
+	// separated by semi-colons or new lines that aren't inside brackets or strings
+	fn raw_group(&mut self) -> Token {
+		let opening_pos = self.index;
+		let num_chars = self.chars.len();
+		let mut tokens:Vec<Token> = Vec::new();
+
+		let mut depth:usize = 0;
+		let mut quote:Option<char> = None;
+		let mut start:Option<usize> = None;
+		let mut end:usize = 0;
+		let mut found_closing = false;
+
+		self.index += 1;
+		while self.index < num_chars {
+			let c = self.chars[self.index];
+
+			if let Some(q) = quote {
+				if c == '\\' {
+					self.index += 1;
+				} else if c == q {
+					quote = None;
+				}
+			}
+			// a comment can only take the place of a command, anywhere else `//` is part of it, like in a URL
+			else if start.is_none() && c == '/' && self.chars.get(self.index+1) == Some(&'/') {
+				while self.index < num_chars && self.chars[self.index] != '\n' {
+					self.index += 1;
+				}
+				continue;
+			}
+			else if start.is_none() && c == '/' && self.chars.get(self.index+1) == Some(&'*') {
+				self.block_comment();
+				continue;
+			}
+			// single quotes are only strings in NBT, otherwise they are apostrophes
+			else if c == '"' || (c == '\'' && depth > 0) {
+				quote = Some(c);
+			}
+			else if c == '(' || c == '[' || c == '{' {
+				depth += 1;
+			}
+			else if (c == ')' || c == ']' || c == '}') && depth > 0 {
+				depth -= 1;
+			}
+			else if depth == 0 && (c == '}' || c == ';' || c == '\n') {
+				if let Some(start) = start.take() {
+					let scope = self.scope(start, end);
+					let value = self.chars[start..end].iter().collect();
+					tokens.push(Token::raw(value, scope));
+				}
+				if c == ';' {
+					tokens.push(Token::sep(c, self.scope(self.index, self.index+1)));
+				}
+				if c == '}' {
+					found_closing = true;
+					self.index += 1;
+					break;
+				}
+				self.index += 1;
+				continue;
+			}
+
+			if !c.is_whitespace() {
+				if start.is_none() { start = Some(self.index) }
+				end = (self.index+1).min(num_chars);
+			}
+			self.index += 1;
+		}
+
+		if !found_closing {
+			let scope = self.scope(opening_pos, opening_pos+1);
+			error(scope, ErrorInfo {
+				cause: "missing closing delimiter",
+				pointer: "unsafe block doesn't have a matching closing bracket",
+				context: Some("unsafe block"),
+				help: None,
+			}).fatal();
+		}
+		tokens.push(Token::end(self.len_scope(1)));
+		let scope = self.scope(opening_pos, self.index);
+		Token::group(Delimiter::CurlyBrackets, tokens, scope)
+	}
+
 	fn len_scope(&self, len:usize) -> SrcScope {
-		SrcScope { file: self.file, start: self.index-len, end: self.index }
+		SrcScope { file: self.file.clone(), start: self.index-len, end: self.index }
 	}
 	fn scope(&self, start:usize, end:usize) -> SrcScope {
-		SrcScope { file: self.file, start, end }
+		SrcScope { file: self.file.clone(), start, end }
 	}
 }
 
+// the symbols made of two characters, every other symbol is a single character
+const OPERATORS:[[char; 2]; 11] = [
+	['-', '>'], ['.', '.'],
+	['=', '='], ['!', '='], ['<', '='], ['>', '='],
+	['+', '='], ['-', '='], ['*', '='], ['/', '='], ['%', '='],
+];
+
+// keywords that can be followed by a negative number
+const KEYWORDS:[&str; 3] = ["if", "while", "in"];
+
+// whether a token can be the left side of a subtraction
+fn ends_value(token:&Token) -> bool {
+	match token {
+		Token::Group(group) => group.delimiter != Delimiter::CurlyBrackets,
+		Token::Ident(ident) if KEYWORDS.contains(&ident.value.as_str()) => false,
+		token => token.is_ident() || token.is_number() || token.is_string() || token.is_selector(),
+	}
+}
 fn is_ident_sep(c:char) -> bool {
 	c.is_whitespace() || is_sep(c) || is_symbol(c) || is_delimiter(c)
 }
@@ -187,32 +601,175 @@ fn is_sep(c:char) -> bool {
 fn is_symbol(c:char) -> bool {
 	c == '+' || c == '-' || c == '*' || c == '/' ||
 	c == '%' || c == '=' || c == '.' || c == '>' ||
-	c == '<'
+	c == '<' || c == '!'
 }
+// `<` and `>` are always symbols, the parser groups them where a type is expected
 fn is_delimiter(c:char) -> bool {
-	match c {
-		'(' | ')' | '{' | '}' | '[' | ']' | '<' | '>' => true,
-		_ => false,
-	}
+	matches!(c, '(' | ')' | '{' | '}' | '[' | ']')
 }
 fn delimiter(c:char) -> Option<Delimiter> {
 	match c {
 		'(' => Some(Delimiter::Parentheses),
 		'{' => Some(Delimiter::CurlyBrackets),
 		'[' => Some(Delimiter::Brackets),
-		'<' => Some(Delimiter::AngledBrackets),
 		_ => None,
 	}
 }
 fn starts_num(c:char) -> bool {
-	c.is_digit(10)
+	c.is_ascii_digit()
 }
 fn in_num(c:char) -> bool {
-	c.is_digit(10) || c == '_'
+	c.is_ascii_digit() || c == '_'
 }
 fn starts_ident(c:char) -> bool {
 	c.is_alphabetic() || c == '_' || c == '$'
 }
 fn in_ident(c:char) -> bool {
-	c.is_alphabetic() || c == '_' || c.is_digit(10)
+	c.is_alphabetic() || c == '_' || c.is_ascii_digit()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::debug::errors::fatal_scope;
+	use crate::lexer::files::SourceMap;
+
+	fn lex(source:&str) -> Vec<Token> {
+		let mut sources = SourceMap::new();
+		let file = sources.add("test.mccs", source);
+		Lexer::new(file).lex()
+	}
+
+	// the only token of the source, as it's debugged
+	fn token(source:&str) -> String {
+		let tokens = lex(source);
+		assert_eq!(tokens.len(), 2, "expected a single token in {:?}", tokens);
+		format!("{:?}", tokens[0])
+	}
+
+	fn string(source:&str) -> String {
+		match lex(source).remove(0) {
+			Token::Str(string) => string.value,
+			token => panic!("expected a string, got {:?}", token),
+		}
+	}
+
+	#[test]
+	fn numbers_with_suffixes() {
+		assert_eq!(token("1_000"), "Int(1000)");
+		assert_eq!(token("-2147483648"), "Int(-2147483648)");
+		assert_eq!(token("0x1F"), "Int(31)");
+		assert_eq!(token("5b"), "Byte(5)");
+		assert_eq!(token("-128b"), "Byte(-128)");
+		assert_eq!(token("3s"), "Short(3)");
+		assert_eq!(token("7L"), "Long(7)");
+		assert_eq!(token("1.5f"), "Float(1.5)");
+		assert_eq!(token("2.5"), "Double(2.5)");
+		assert_eq!(token("1d"), "Double(1.0)");
+	}
+
+	#[test]
+	fn binary_prefix_and_byte_suffix() {
+		// `0b` followed by digits is binary, on its own it's the byte 0
+		assert_eq!(token("0b101"), "Int(5)");
+		assert_eq!(token("0b"), "Byte(0)");
+		assert_eq!(token("0b1b"), "Byte(1)");
+		assert_eq!(fatal_scope(|| lex("0b2")), (0, 2));
+	}
+
+	#[test]
+	fn integer_boundaries() {
+		assert_eq!(token("2147483647"), "Int(2147483647)");
+		assert_eq!(token("-2147483648"), "Int(-2147483648)");
+		assert_eq!(fatal_scope(|| lex("2147483648")), (0, 10));
+		assert_eq!(fatal_scope(|| lex("-2147483649")), (0, 11));
+		assert_eq!(token("127b"), "Byte(127)");
+		assert_eq!(fatal_scope(|| lex("-129b")), (0, 5));
+		assert_eq!(token("-32768s"), "Short(-32768)");
+		assert_eq!(fatal_scope(|| lex("32768s")), (0, 6));
+	}
+
+	#[test]
+	fn numbers_out_of_range() {
+		assert_eq!(fatal_scope(|| lex("x = 128b")), (4, 8));
+		assert_eq!(fatal_scope(|| lex("40000s")), (0, 6));
+		assert_eq!(fatal_scope(|| lex("99999999999999999999L")), (0, 21));
+		assert_eq!(fatal_scope(|| lex("340282366920938463463374607431768211456.0f")), (0, 42));
+	}
+
+	#[test]
+	fn invalid_number_suffixes() {
+		// the suffix is pointed at, or the whole number when it can't have decimals
+		assert_eq!(fatal_scope(|| lex("5q")), (1, 2));
+		assert_eq!(fatal_scope(|| lex("0b1f")), (3, 4));
+		assert_eq!(fatal_scope(|| lex("1.5b")), (0, 4));
+		assert_eq!(fatal_scope(|| lex("0x")), (0, 2));
+	}
+
+	#[test]
+	fn string_escapes() {
+		assert_eq!(string(r#""a\"b""#), "a\"b");
+		assert_eq!(string(r#""a\\b""#), "a\\b");
+		assert_eq!(string(r#""a\nb""#), "a\nb");
+		assert_eq!(string(r#""\u{2764}!""#), "\u{2764}!");
+		// an escape is one char of the value, which points at the whole escape
+		let Token::Str(escaped) = lex(r#""x\u{41}y""#).remove(0) else { panic!("expected a string") };
+		assert_eq!(escaped.spans, vec![(1, 2), (2, 8), (8, 9)]);
+	}
+
+	#[test]
+	fn invalid_string_escapes() {
+		assert_eq!(fatal_scope(|| lex(r#""a\qb""#)), (3, 4));
+		assert_eq!(fatal_scope(|| lex(r#""\u41""#)), (3, 4));
+		assert_eq!(fatal_scope(|| lex(r#""\u{4g}""#)), (5, 6));
+		assert_eq!(fatal_scope(|| lex(r#""\u{110000}""#)), (4, 10));
+		// surrogates aren't characters on their own
+		assert_eq!(fatal_scope(|| lex(r#""\u{D800}""#)), (4, 8));
+		assert_eq!(fatal_scope(|| lex(r#""\u{1234567}""#)), (4, 11));
+		assert_eq!(fatal_scope(|| lex("\"abc\nx\"")), (0, 1));
+	}
+
+	#[test]
+	fn multiline_strings_are_dedented() {
+		assert_eq!(string("\"\"\"\n\t\tfirst\n\t\t\tsecond\n\t\"\"\""), "\tfirst\n\t\tsecond");
+		// blank lines don't need the indentation
+		assert_eq!(string("\"\"\"\n\ta\n\n\tb\n\t\"\"\""), "a\n\nb");
+	}
+
+	#[test]
+	fn mixed_indentation_in_multiline_strings() {
+		// spaces don't match the tab before the closing quotes, the error points at the opening quotes
+		assert_eq!(fatal_scope(|| lex("x = \"\"\"\n    a\n\t\"\"\"")), (4, 7));
+		assert_eq!(fatal_scope(|| lex("\"\"\"\n\ta\n  b\n\t\"\"\"")), (0, 3));
+	}
+
+	fn raw_commands(block:&str) -> Vec<String> {
+		let tokens = lex(format!("unsafe {}", block).as_str());
+		let Token::Group(group) = &tokens[1] else { panic!("expected a group, got {:?}", tokens[1]) };
+		group.tokens.iter().filter_map(|token| match token {
+			Token::Raw(raw) => Some(raw.value.clone()),
+			_ => None,
+		}).collect()
+	}
+
+	#[test]
+	fn comments_between_raw_commands_are_dropped() {
+		let commands = raw_commands("{\n\t// first\nsay hi\n/* block\ncomment */ say there; // after\n}");
+		assert_eq!(commands, vec!["say hi", "say there"]);
+	}
+
+	#[test]
+	fn slashes_in_raw_commands_are_kept() {
+		assert_eq!(raw_commands("{ say see https://example.com }"), vec!["say see https://example.com"]);
+		assert_eq!(raw_commands("{\n\tsay see https://example.com\n}"), vec!["say see https://example.com"]);
+		assert_eq!(raw_commands("{ say a /* b */ }"), vec!["say a /* b */"]);
+	}
+
+	#[test]
+	fn comments_in_raw_json_are_kept() {
+		let commands = raw_commands(r#"{ tellraw @a {"text":"a // b"} }"#);
+		assert_eq!(commands, vec![r#"tellraw @a {"text":"a // b"}"#]);
+		let commands = raw_commands("{ data merge entity @s {CustomName:'// not a comment'} }");
+		assert_eq!(commands, vec!["data merge entity @s {CustomName:'// not a comment'}"]);
+	}
 }
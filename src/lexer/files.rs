@@ -1,49 +1,68 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Formatter};
 use std::fs::File;
 use std::io::Read;
+use std::sync::Arc;
 
-static mut FILES:Option<FileManager> = None;
-
-pub struct FileManager {
-	paths: Vec<String>,
-	sources: Vec<String>,
+// a file and its source, shared by the tokens and errors that point into it
+struct SourceFile {
+	path: String,
+	source: String,
 }
 
-pub fn init() {
-	unsafe { FILES = Some(FileManager {
-		paths: Vec::new(),
-		sources: Vec::new(),
-	}) }
-}
-pub fn file_mgr() -> &'static FileManager {
-	unsafe { FILES.as_ref().unwrap() }
-}
-pub fn mut_file_mgr() -> &'static mut FileManager {
-	unsafe { FILES.as_mut().unwrap() }
-}
-pub fn load_file_src(path:&str, source:&str) {
-	let mgr = mut_file_mgr();
-	mgr.paths.push(path.to_owned());
-	mgr.sources.push(source.to_owned());
-}
-pub fn load_file(path:&str) {
-	let source = read_file(path);
-	load_file_src(path, source.as_str());
+// owns the files of a project.
+// replacing or removing a file doesn't change the tokens lexed from it, they keep the old source
+#[derive(Default)]
+pub struct SourceMap {
+	files: Vec<Option<FileRef>>,
 }
-pub fn ref_file(index:usize) -> FileRef {
-	let mgr = file_mgr();
-	assert!(index < mgr.paths.len());
-	FileRef(index)
+impl SourceMap {
+	pub fn new() -> Self {
+		SourceMap { files: Vec::new() }
+	}
+
+	pub fn add(&mut self, path:&str, source:&str) -> FileRef {
+		let file = FileRef(Arc::new(SourceFile { path: path.to_owned(), source: source.to_owned() }));
+		self.files.push(Some(file.clone()));
+		file
+	}
+	pub fn load(&mut self, path:&str) -> FileRef {
+		let source = read_file(path);
+		self.add(path, source.as_str())
+	}
+	// returns the new version of the file, which has to be lexed again
+	pub fn replace(&mut self, file:&FileRef, source:&str) -> FileRef {
+		let index = self.index(file);
+		let new = FileRef(Arc::new(SourceFile { path: file.0.path.clone(), source: source.to_owned() }));
+		self.files[index] = Some(new.clone());
+		new
+	}
+	pub fn remove(&mut self, file:&FileRef) {
+		let index = self.index(file);
+		self.files[index] = None;
+	}
+
+	pub fn find(&self, path:&str) -> Option<FileRef> {
+		self.files().find(|file| file.path() == path).cloned()
+	}
+	pub fn files(&self) -> impl Iterator<Item = &FileRef> {
+		self.files.iter().flatten()
+	}
+
+	fn index(&self, file:&FileRef) -> usize {
+		self.files.iter()
+			.position(|existing| existing.as_ref() == Some(file))
+			.expect("file isn't part of this source map")
+	}
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct FileRef(usize);
+#[derive(Clone)]
+pub struct FileRef(Arc<SourceFile>);
 impl FileRef {
-	pub fn source(&self) -> &'static String {
-		&file_mgr().sources[self.0]
+	pub fn source(&self) -> &str {
+		self.0.source.as_str()
 	}
-	pub fn path(&self) -> &'static String {
-		&file_mgr().paths[self.0]
+	pub fn path(&self) -> &str {
+		self.0.path.as_str()
 	}
 
 	pub fn line_source(&self, line:usize) -> String {
@@ -66,14 +85,24 @@ impl FileRef {
 		panic!("couldn't find index of line start")
 	}
 }
+impl PartialEq for FileRef {
+	fn eq(&self, other:&Self) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+	}
+}
+impl Debug for FileRef {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "FileRef({})", self.path())
+	}
+}
 
 pub fn read_file(path:&str) -> String {
 	let mut src = String::new();
-	let mut file = File::open(path).expect(format!(
+	let mut file = File::open(path).unwrap_or_else(|_| panic!(
 		"failed to open file {}", path
-	).as_str());
-	file.read_to_string(&mut src).expect(format!(
+	));
+	file.read_to_string(&mut src).unwrap_or_else(|_| panic!(
 		"failed to read file {}", path
-	).as_str());
+	));
 	src
 }
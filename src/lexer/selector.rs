@@ -0,0 +1,418 @@
+use crate::debug::errors::{error, ErrorInfo, Help};
+use crate::lexer::Lexer;
+use crate::lexer::tokens::{SelectorArg, Token};
+
+// `@n` is left out, it only exists in newer versions than `PACK_FORMAT`.
+// what each of them can select is in `Executor::of_selector`
+const BASES:[char; 5] = ['a', 'p', 'r', 's', 'e'];
+
+// the shape of the value of a selector argument
+enum Shape {
+	// a number range like `1..5`, `..5` or `1..`
+	Range { ints: bool, negative: bool },
+	Double,
+	Limit,
+	// a resource location, or a tag of them starting with `#`
+	Resource,
+	// an unquoted word or a string, which can be empty
+	Name,
+	// `{<objective>=<range>,...}`
+	Scores,
+	// `{...}`
+	Compound,
+	Options(&'static [&'static str]),
+}
+
+// the arguments a selector can have, and whether they can be negated with `!`
+fn shape(key:&str) -> Option<(Shape, bool)> {
+	Some(match key {
+		"type" | "predicate" => (Shape::Resource, true),
+		"tag" | "team" | "name" => (Shape::Name, true),
+		"nbt" => (Shape::Compound, true),
+		"advancements" => (Shape::Compound, false),
+		"scores" => (Shape::Scores, false),
+		"distance" => (Shape::Range { ints: false, negative: false }, false),
+		"level" => (Shape::Range { ints: true, negative: false }, false),
+		"x_rotation" | "y_rotation" => (Shape::Range { ints: false, negative: true }, false),
+		"x" | "y" | "z" | "dx" | "dy" | "dz" => (Shape::Double, false),
+		"limit" => (Shape::Limit, false),
+		"sort" => (Shape::Options(&["nearest", "furthest", "random", "arbitrary"]), false),
+		"gamemode" => (Shape::Options(&["survival", "creative", "adventure", "spectator"]), true),
+		_ => return None,
+	})
+}
+
+// arguments that select by what an entity has can be given more than once,
+// the others only when every one of them is negated
+fn repeatable(key:&str) -> bool {
+	matches!(key, "tag" | "nbt" | "predicate")
+}
+
+impl Lexer {
+	// `@<base>`, optionally followed by its arguments in brackets
+	pub(super) fn selector(&mut self) -> Token {
+		let start = self.index;
+		self.index += 1;
+		let base_start = self.index;
+		while self.chars.get(self.index).is_some_and(|c| c.is_alphabetic()) {
+			self.index += 1;
+		}
+		let base:String = self.chars[base_start..self.index].iter().collect();
+		let mut chars = base.chars();
+		let (Some(base), None) = (chars.next(), chars.next()) else {
+			error(self.scope(start, self.index.max(start+1)), ErrorInfo {
+				cause: "invalid selector",
+				pointer: "expected one of `@a`, `@p`, `@r`, `@s` or `@e`",
+				context: Some("selector"),
+				help: None,
+			}).fatal();
+		};
+		if !BASES.contains(&base) {
+			error(self.scope(base_start, self.index), ErrorInfo {
+				cause: "invalid selector",
+				pointer: "expected one of `@a`, `@p`, `@r`, `@s` or `@e`",
+				context: Some("selector"),
+				help: None,
+			}).fatal();
+		}
+		let mut args = Vec::new();
+		if self.chars.get(self.index) == Some(&'[') {
+			args = self.selector_args();
+		}
+		Token::selector(base, args, self.scope(start, self.index))
+	}
+
+	// expects the current character to be the opening bracket
+	fn selector_args(&mut self) -> Vec<SelectorArg> {
+		let opening = self.index;
+		let mut args:Vec<SelectorArg> = Vec::new();
+		self.index += 1;
+		loop {
+			self.skip_whitespace();
+			match self.chars.get(self.index) {
+				Some(']') => break,
+				None => self.unclosed_selector(opening),
+				_ => {}
+			}
+			let arg = self.selector_arg(opening);
+			self.check_selector_arg(&arg, &args);
+			args.push(arg);
+			self.skip_whitespace();
+			match self.chars.get(self.index) {
+				Some(',') => self.index += 1,
+				Some(']') => break,
+				Some(_) => error(self.scope(self.index, self.index+1), ErrorInfo {
+					cause: "invalid selector argument",
+					pointer: "expected `,` or `]`",
+					context: Some("selector"),
+					help: None,
+				}).fatal(),
+				None => self.unclosed_selector(opening),
+			}
+		}
+		self.index += 1;
+		args
+	}
+
+	fn selector_arg(&mut self, opening:usize) -> SelectorArg {
+		let key_start = self.index;
+		while self.chars.get(self.index).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+			self.index += 1;
+		}
+		let key_scope = self.scope(key_start, self.index);
+		let key:String = self.chars[key_start..self.index].iter().collect();
+		self.skip_whitespace();
+		if key.is_empty() || self.chars.get(self.index) != Some(&'=') {
+			if self.index >= self.chars.len() { self.unclosed_selector(opening) }
+			error(self.scope(self.index, self.index+1), ErrorInfo {
+				cause: "invalid selector argument",
+				pointer: if key.is_empty() { "expected the name of an argument" } else { "expected `=`" },
+				context: Some("selector"),
+				help: Some(Help {
+					msg: "selector arguments are written as `<name>=<value>`".to_owned(),
+					source: "@e[type=cow,tag=!tamed]".to_owned(),
+					line: 0,
+				}),
+			}).fatal();
+		}
+		self.index += 1;
+		self.skip_whitespace();
+		let negated = self.chars.get(self.index) == Some(&'!');
+		if negated {
+			self.index += 1;
+			self.skip_whitespace();
+		}
+
+		// the value ends at a `,` or `]` that isn't in a string or brackets
+		let value_start = self.index;
+		let mut value_end = self.index;
+		let mut depth:usize = 0;
+		let mut quote:Option<char> = None;
+		while let Some(&c) = self.chars.get(self.index) {
+			if let Some(q) = quote {
+				if c == '\\' { self.index += 1 }
+				else if c == q { quote = None }
+			}
+			else if c == '"' || c == '\'' { quote = Some(c) }
+			else if c == '{' || c == '[' { depth += 1 }
+			else if (c == '}' || c == ']') && depth > 0 { depth -= 1 }
+			else if depth == 0 && (c == ',' || c == ']') { break }
+			self.index += 1;
+			if !c.is_whitespace() { value_end = self.index }
+		}
+		if self.index >= self.chars.len() { self.unclosed_selector(opening) }
+		let value = self.chars[value_start..value_end].iter().collect();
+		SelectorArg {
+			key, key_scope, negated, value,
+			value_scope: self.scope(value_start, value_end),
+		}
+	}
+
+	fn check_selector_arg(&self, arg:&SelectorArg, previous:&[SelectorArg]) {
+		let Some((shape, negatable)) = shape(arg.key.as_str()) else {
+			error(arg.key_scope.clone(), ErrorInfo {
+				cause: "unknown selector argument",
+				pointer: "this isn't an argument of selectors",
+				context: Some("selector"),
+				help: None,
+			}).fatal();
+		};
+		if arg.negated && !negatable {
+			error(arg.key_scope.join(arg.value_scope.clone()), ErrorInfo {
+				cause: "invalid selector argument",
+				pointer: format!("`{}` can't be negated", arg.key).as_str(),
+				context: Some("selector"),
+				help: None,
+			}).fatal();
+		}
+		let existing = previous.iter().find(|existing| existing.key == arg.key);
+		if let Some(existing) = existing {
+			let allowed = repeatable(arg.key.as_str()) || (negatable && arg.negated && existing.negated);
+			if !allowed {
+				error(arg.key_scope.clone(), ErrorInfo {
+					cause: "duplicate selector argument",
+					pointer: if negatable {
+						format!("`{}` can only be given more than once when every one of them is negated", arg.key)
+					} else {
+						format!("`{}` can only be given once", arg.key)
+					}.as_str(),
+					context: Some("selector"),
+					help: None,
+				}).note(existing.key_scope.clone(), "given here").fatal();
+			}
+		}
+		let value = arg.value.as_str();
+		let valid = match shape {
+			Shape::Range { ints, negative } => range(value, ints, negative),
+			Shape::Double => value.parse::<f64>().is_ok_and(f64::is_finite),
+			Shape::Limit => value.parse::<u32>().is_ok_and(|limit| limit > 0 && limit <= i32::MAX as u32),
+			Shape::Resource => resource(value),
+			Shape::Name => name(value),
+			Shape::Scores => {
+				self.check_scores(arg);
+				true
+			}
+			Shape::Compound => value.starts_with('{') && value.ends_with('}'),
+			Shape::Options(options) => options.contains(&value),
+		};
+		if valid { return }
+		let expected = match shape {
+			Shape::Range { ints: true, .. } => "a range of integers, like `1..5`, `..5` or `1`".to_owned(),
+			Shape::Range { negative: true, .. } => "a range of numbers, like `-45..45`".to_owned(),
+			Shape::Range { .. } => "a range of positive numbers, like `..5.5`".to_owned(),
+			Shape::Double => "a number".to_owned(),
+			Shape::Limit => "a positive integer".to_owned(),
+			Shape::Resource => "a resource location, like `minecraft:cow` or `#minecraft:skeletons`".to_owned(),
+			Shape::Name => "a name, or a string in quotes".to_owned(),
+			Shape::Scores => unreachable!(),
+			Shape::Compound => "NBT in curly brackets".to_owned(),
+			Shape::Options(options) => format!("one of `{}`", options.join("`, `")),
+		};
+		error(arg.value_scope.clone(), ErrorInfo {
+			cause: "invalid selector argument",
+			pointer: format!("expected {}", expected).as_str(),
+			context: Some("selector"),
+			help: None,
+		}).fatal();
+	}
+
+	// `{<objective>=<range>,...}`, errors point at the part of it that's invalid
+	fn check_scores(&self, arg:&SelectorArg) {
+		let chars:Vec<char> = arg.value.chars().collect();
+		let start = arg.value_scope.start;
+		let invalid = |from:usize, to:usize, pointer:&str| -> ! {
+			error(self.scope(start+from, start+to.max(from+1)), ErrorInfo {
+				cause: "invalid selector argument",
+				pointer,
+				context: Some("selector"),
+				help: Some(Help {
+					msg: "`scores` takes the ranges of objectives in curly brackets".to_owned(),
+					source: "@a[scores={kills=1..,deaths=..0}]".to_owned(),
+					line: 0,
+				}),
+			}).fatal()
+		};
+		if chars.first() != Some(&'{') || chars.last() != Some(&'}') {
+			invalid(0, chars.len(), "expected the scores in curly brackets");
+		}
+		let mut i = 1;
+		while i < chars.len()-1 {
+			let part_start = i;
+			while i < chars.len()-1 && chars[i] != ',' { i += 1 }
+			let part:String = chars[part_start..i].iter().collect();
+			let trimmed = part.trim();
+			if !trimmed.is_empty() || i < chars.len()-1 {
+				let Some((objective, value)) = part.split_once('=') else {
+					invalid(part_start, i, "expected `<objective>=<range>`");
+				};
+				let objective = objective.trim();
+				let valid_objective = !objective.is_empty() && objective.chars()
+					.all(|c| c.is_ascii_alphanumeric() || "_-.+".contains(c));
+				if !valid_objective {
+					invalid(part_start, part_start+objective.chars().count().max(1), "expected the name of an objective");
+				}
+				if !range(value.trim(), true, true) {
+					let value_start = part_start + part.find('=').unwrap() + 1;
+					invalid(value_start, i, "expected a range of integers, like `1..5`, `..5` or `1`");
+				}
+			}
+			i += 1;
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.chars.get(self.index).is_some_and(|c| c.is_whitespace()) {
+			self.index += 1;
+		}
+	}
+
+	fn unclosed_selector(&self, opening:usize) -> ! {
+		error(self.scope(opening, opening+1), ErrorInfo {
+			cause: "missing closing delimiter",
+			pointer: "the arguments of this selector don't have a matching `]`",
+			context: Some("selector"),
+			help: None,
+		}).fatal();
+	}
+}
+
+// `<min>..<max>`, where either side can be left out, or a single number
+fn range(value:&str, ints:bool, negative:bool) -> bool {
+	let number = |bound:&str| -> Option<f64> {
+		let number = if ints {
+			bound.parse::<i32>().ok().map(f64::from)
+		} else {
+			bound.parse::<f64>().ok().filter(|number| number.is_finite())
+		};
+		number.filter(|number| negative || *number >= 0.0)
+	};
+	match value.split_once("..") {
+		None => number(value).is_some(),
+		Some(("", "")) => false,
+		Some((min, "")) => number(min).is_some(),
+		Some(("", max)) => number(max).is_some(),
+		Some((min, max)) => match (number(min), number(max)) {
+			(Some(min), Some(max)) => min <= max,
+			_ => false,
+		}
+	}
+}
+
+fn resource(value:&str) -> bool {
+	let value = value.strip_prefix('#').unwrap_or(value);
+	let (namespace, path) = value.split_once(':').unwrap_or(("minecraft", value));
+	!namespace.is_empty() && !path.is_empty()
+		&& namespace.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.".contains(c))
+		&& path.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-./".contains(c))
+}
+
+fn name(value:&str) -> bool {
+	let quoted = value.len() >= 2 && (
+		(value.starts_with('"') && value.ends_with('"'))
+		|| (value.starts_with('\'') && value.ends_with('\''))
+	);
+	quoted || value.chars().all(|c| c.is_ascii_alphanumeric() || "_-.+".contains(c))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::debug::errors::fatal_scope;
+	use crate::lexer::files::SourceMap;
+
+	fn lex(source:&str) -> Vec<Token> {
+		let mut sources = SourceMap::new();
+		let file = sources.add("test.mccs", source);
+		Lexer::new(file).lex()
+	}
+
+	fn args(source:&str) -> Vec<(String, bool, String)> {
+		match lex(source).remove(0) {
+			Token::Selector(selector) => selector.args.into_iter()
+				.map(|arg| (arg.key, arg.negated, arg.value))
+				.collect(),
+			token => panic!("expected a selector, got {:?}", token),
+		}
+	}
+
+	#[test]
+	fn ranges() {
+		assert!(range("1..5", true, false));
+		assert!(range("..5", true, false));
+		assert!(range("1..", true, false));
+		assert!(range("3", true, false));
+		assert!(range("-45.5..45", false, true));
+		assert!(range("..5.5", false, false));
+		assert!(!range("..", true, true));
+		assert!(!range("5..1", true, true));
+		assert!(!range("1.5", true, true));
+		assert!(!range("-1..", false, false));
+		assert!(!range("a..b", false, true));
+	}
+
+	#[test]
+	fn valid_selector_args() {
+		let given = args("@e[type=#minecraft:skeletons, tag=!a, tag=!b, scores={kills=1.., deaths=..0}, distance=..5.5, limit=1]");
+		let keys:Vec<&str> = given.iter().map(|(key, _, _)| key.as_str()).collect();
+		assert_eq!(keys, vec!["type", "tag", "tag", "scores", "distance", "limit"]);
+		assert_eq!(given[1], ("tag".to_owned(), true, "a".to_owned()));
+		assert_eq!(given[3].2, "{kills=1.., deaths=..0}");
+		// repeatable arguments can be given more than once even when they aren't negated
+		assert_eq!(args("@s[tag=a,tag=b,nbt={x:1b}]").len(), 3);
+	}
+
+	#[test]
+	fn invalid_selector_args() {
+		// unknown arguments and duplicates point at the name
+		assert_eq!(fatal_scope(|| lex("@e[foo=1]")), (3, 6));
+		assert_eq!(fatal_scope(|| lex("@e[limit=1,limit=2]")), (11, 16));
+		assert_eq!(fatal_scope(|| lex("@e[team=a,team=!b]")), (10, 14));
+		// negating an argument that can't be points at all of it
+		assert_eq!(fatal_scope(|| lex("@e[limit=!1]")), (3, 11));
+		// values of the wrong shape point at the value
+		assert_eq!(fatal_scope(|| lex("@e[sort=closest]")), (8, 15));
+		assert_eq!(fatal_scope(|| lex("@e[limit=0]")), (9, 10));
+		assert_eq!(fatal_scope(|| lex("@e[level=1.5]")), (9, 12));
+		assert_eq!(fatal_scope(|| lex("@e[type=Cow]")), (8, 11));
+		assert_eq!(fatal_scope(|| lex("@e[type]")), (7, 8));
+		assert_eq!(fatal_scope(|| lex("@e[type=cow")), (2, 3));
+	}
+
+	#[test]
+	fn invalid_bases() {
+		assert_eq!(fatal_scope(|| lex("@n")), (1, 2));
+		assert_eq!(fatal_scope(|| lex("@x[tag=a]")), (1, 2));
+		assert_eq!(fatal_scope(|| lex("@ab")), (0, 3));
+	}
+
+	#[test]
+	fn invalid_scores() {
+		// errors point at the part of the scores that's wrong
+		assert_eq!(fatal_scope(|| lex("@a[scores=kills]")), (10, 15));
+		assert_eq!(fatal_scope(|| lex("@a[scores={kills=a}]")), (17, 18));
+		assert_eq!(fatal_scope(|| lex("@a[scores={kills=1,deaths=2..1}]")), (26, 30));
+		assert_eq!(fatal_scope(|| lex("@a[scores={=1}]")), (11, 12));
+		assert_eq!(fatal_scope(|| lex("@a[scores={kills}]")), (11, 16));
+	}
+}
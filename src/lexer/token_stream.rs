@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 use crate::lexer::tokens::Token;
-use crate::parser::Path;
+use crate::parser::ResLoc;
 
 pub trait TokenList {
 	fn as_list(&self) -> &Vec<Token>;
@@ -39,30 +39,28 @@ impl TokenIter {
 	pub fn prev(&self) -> Option<&Token> {
 		self.tokens.get(self.index-1)
 	}
+	#[allow(clippy::should_implement_trait)]
 	pub fn next(&mut self) -> &Token {
 		let result = &self.tokens[self.index];
 		if !result.is_end() { self.index += 1; }
 		result
 	}
 
-	pub fn get_path(&mut self, context:Option<&str>) -> Option<Path> {
-		let first_token = self.prev().unwrap().clone();
-		let Some(first) = first_token.as_ident() else { return None };
-		let mut path = Path::new(first.clone());
-		while let Some(token) = self.next().as_non_end() {
-			let sep = token.expect_sep(
-				context, None,
-			).fatal();
-			let part = self.next().expect_ident(
-				context, None,
-			).fatal();
-			path.push_part(sep, part);
+	// expects the first part of the resource location to be the previous token,
+	// stops at the first token that doesn't continue it
+	pub fn get_res_loc(&mut self, context:&str) -> Option<ResLoc> {
+		let first = self.prev()?;
+		if !first.is_ident() { return None }
+		let mut tokens = vec![first.clone()];
+		while ResLoc::continues(&tokens, self.current()) {
+			tokens.push(self.next().clone());
 		}
-		Some(path)
+		Some(ResLoc::new(&tokens, context))
 	}
+
 }
 
-pub fn traverse_tokens<F: Fn(&mut Token)>(tokens:&mut Vec<Token>, f:&F) {
+pub fn traverse_tokens<F: Fn(&mut Token)>(tokens:&mut [Token], f:&F) {
 	let mut i = 0;
 	while i < tokens.len() {
 		f(&mut tokens[i]);
@@ -1,30 +1,56 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use crate::debug::errors::{CompileResult, error, ErrorInfo, Help};
-use crate::files::FileRef;
+use crate::lexer::files::FileRef;
 
 #[derive(Clone)]
 pub enum Token {
 	Ident(Ident),
 	Str(Str),
 	Int(Int),
+	Byte(Byte),
+	Short(Short),
+	Long(Long),
+	Float(Float),
+	Double(Double),
 	Sep(Sep),
+	Selector(Selector),
 	Symbol(Symbol),
 	End(End),
 	Group(Group),
+	Raw(Raw),
+	Doc(Doc),
 }
 impl Token {
 	pub fn ident(value:String, scope:SrcScope) -> Self {
 		Self::Ident(Ident { value, scope })
 	}
-	pub fn string(value:String, scope:SrcScope) -> Self {
-		Self::Str(Str { value, scope })
+	pub fn string(value:String, spans:Vec<(usize, usize)>, scope:SrcScope) -> Self {
+		Self::Str(Str { value, spans, scope })
 	}
 	pub fn int(value:i32, scope:SrcScope) -> Self {
 		Self::Int(Int { value, scope })
 	}
+	pub fn byte(value:i8, scope:SrcScope) -> Self {
+		Self::Byte(Byte { value, scope })
+	}
+	pub fn short(value:i16, scope:SrcScope) -> Self {
+		Self::Short(Short { value, scope })
+	}
+	pub fn long(value:i64, scope:SrcScope) -> Self {
+		Self::Long(Long { value, scope })
+	}
+	pub fn float(value:f32, scope:SrcScope) -> Self {
+		Self::Float(Float { value, scope })
+	}
+	pub fn double(value:f64, scope:SrcScope) -> Self {
+		Self::Double(Double { value, scope })
+	}
 	pub fn sep(value:char, scope:SrcScope) -> Self {
 		Self::Sep(Sep { value, scope })
 	}
+	pub fn selector(base:char, args:Vec<SelectorArg>, scope:SrcScope) -> Self {
+		Self::Selector(Selector { base, args, scope })
+	}
 	pub fn symbol(value:String, scope:SrcScope) -> Self {
 		Self::Symbol(Symbol { value, scope })
 	}
@@ -34,39 +60,66 @@ impl Token {
 	pub fn group(delimiter:Delimiter, tokens:Vec<Token>, scope:SrcScope) -> Self {
 		Self::Group(Group { delimiter, tokens, scope })
 	}
+	pub fn raw(value:String, scope:SrcScope) -> Self {
+		Self::Raw(Raw { value, scope })
+	}
+	pub fn doc(value:String, scope:SrcScope) -> Self {
+		Self::Doc(Doc { value, scope })
+	}
 
 	pub fn scope(&self) -> SrcScope {
 		match self {
-			Self::Ident(e) => e.scope,
-			Self::Str(e) => e.scope,
-			Self::Int(e) => e.scope,
-			Self::Sep(e) => e.scope,
-			Self::Symbol(e) => e.scope,
-			Self::End(e) => e.scope,
-			Self::Group(e) => e.scope,
+			Self::Ident(e) => e.scope.clone(),
+			Self::Str(e) => e.scope.clone(),
+			Self::Int(e) => e.scope.clone(),
+			Self::Byte(e) => e.scope.clone(),
+			Self::Short(e) => e.scope.clone(),
+			Self::Long(e) => e.scope.clone(),
+			Self::Float(e) => e.scope.clone(),
+			Self::Double(e) => e.scope.clone(),
+			Self::Sep(e) => e.scope.clone(),
+			Self::Selector(e) => e.scope.clone(),
+			Self::Symbol(e) => e.scope.clone(),
+			Self::End(e) => e.scope.clone(),
+			Self::Group(e) => e.scope.clone(),
+			Self::Raw(e) => e.scope.clone(),
+			Self::Doc(e) => e.scope.clone(),
 		}
 	}
 
 	pub fn is_ident(&self) -> bool {
-		if let Self::Ident(_) = self { true } else { false }
+		matches!(self, Self::Ident(_))
 	}
 	pub fn is_string(&self) -> bool {
-		if let Self::Str(_) = self { true } else { false }
+		matches!(self, Self::Str(_))
 	}
 	pub fn is_int(&self) -> bool {
-		if let Self::Int(_) = self { true } else { false }
+		matches!(self, Self::Int(_))
+	}
+	// any number, with or without a suffix
+	pub fn is_number(&self) -> bool {
+		matches!(self, Self::Int(_) | Self::Byte(_) | Self::Short(_) | Self::Long(_) | Self::Float(_) | Self::Double(_))
 	}
 	pub fn is_sep(&self) -> bool {
-		if let Self::Sep(_) = self { true } else { false }
+		matches!(self, Self::Sep(_))
+	}
+	pub fn is_selector(&self) -> bool {
+		matches!(self, Self::Selector(_))
 	}
 	pub fn is_symbol(&self) -> bool {
-		if let Self::Symbol(_) = self { true } else { false }
+		matches!(self, Self::Symbol(_))
 	}
 	pub fn is_end(&self) -> bool {
-		if let Self::End(_) = self { true } else { false }
+		matches!(self, Self::End(_))
 	}
 	pub fn is_group(&self) -> bool {
-		if let Self::Group(_) = self { true } else { false }
+		matches!(self, Self::Group(_))
+	}
+	pub fn is_raw(&self) -> bool {
+		matches!(self, Self::Raw(_))
+	}
+	pub fn is_doc(&self) -> bool {
+		matches!(self, Self::Doc(_))
 	}
 
 	pub fn as_non_end(&self) -> Option<&Token> {
@@ -81,9 +134,27 @@ impl Token {
 	pub fn as_int(&self) -> Option<&Int> {
 		if let Self::Int(e) = self { Some(e) } else { None }
 	}
+	pub fn as_byte(&self) -> Option<&Byte> {
+		if let Self::Byte(e) = self { Some(e) } else { None }
+	}
+	pub fn as_short(&self) -> Option<&Short> {
+		if let Self::Short(e) = self { Some(e) } else { None }
+	}
+	pub fn as_long(&self) -> Option<&Long> {
+		if let Self::Long(e) = self { Some(e) } else { None }
+	}
+	pub fn as_float(&self) -> Option<&Float> {
+		if let Self::Float(e) = self { Some(e) } else { None }
+	}
+	pub fn as_double(&self) -> Option<&Double> {
+		if let Self::Double(e) = self { Some(e) } else { None }
+	}
 	pub fn as_sep(&self) -> Option<&Sep> {
 		if let Self::Sep(e) = self { Some(e) } else { None }
 	}
+	pub fn as_selector(&self) -> Option<&Selector> {
+		if let Self::Selector(e) = self { Some(e) } else { None }
+	}
 	pub fn as_symbol(&self) -> Option<&Symbol> {
 		if let Self::Symbol(e) = self { Some(e) } else { None }
 	}
@@ -93,6 +164,12 @@ impl Token {
 	pub fn as_group(&self) -> Option<&Group> {
 		if let Self::Group(e) = self { Some(e) } else { None }
 	}
+	pub fn as_raw(&self) -> Option<&Raw> {
+		if let Self::Raw(e) = self { Some(e) } else { None }
+	}
+	pub fn as_doc(&self) -> Option<&Doc> {
+		if let Self::Doc(e) = self { Some(e) } else { None }
+	}
 
 	pub fn as_symbol_w(&self, value:&str) -> Option<&Symbol> {
 		if let Self::Symbol(e) = self {
@@ -109,6 +186,11 @@ impl Token {
 			if e.value.as_str() == value { Some(e) } else { None }
 		} else { None }
 	}
+	pub fn as_group_w(&self, delimiter:Delimiter) -> Option<&Group> {
+		if let Self::Group(e) = self {
+			if e.delimiter == delimiter { Some(e) } else { None }
+		} else { None }
+	}
 
 	fn invalid<T: Debug>(
 		&self,
@@ -199,6 +281,18 @@ impl Token {
 			_ => err
 		}
 	}
+	pub fn expect_symbol_w(&self, value:&str, context:Option<&str>, help:Option<Help>) -> CompileResult<Symbol> {
+		let err = self.invalid(format!(
+			"Symbol(\"{}\")", value
+		).as_str(), context, help);
+		match self {
+			Self::Symbol(e) => {
+				if e.value.as_str() == value { CompileResult::Ok(e.clone()) }
+				else { err }
+			}
+			_ => err
+		}
+	}
 	pub fn expect_group_w(&self, delimiter:Delimiter, context:Option<&str>, help:Option<Help>) -> CompileResult<Group> {
 		let err = self.invalid(format!(
 			"Group<{:?}>", delimiter
@@ -218,10 +312,18 @@ impl Debug for Token {
 			Self::Ident(e) => Debug::fmt(e, f),
 			Self::Str(e) => Debug::fmt(e, f),
 			Self::Int(e) => Debug::fmt(e, f),
+			Self::Byte(e) => Debug::fmt(e, f),
+			Self::Short(e) => Debug::fmt(e, f),
+			Self::Long(e) => Debug::fmt(e, f),
+			Self::Float(e) => Debug::fmt(e, f),
+			Self::Double(e) => Debug::fmt(e, f),
 			Self::Sep(e) => Debug::fmt(e, f),
+			Self::Selector(e) => Debug::fmt(e, f),
 			Self::Symbol(e) => Debug::fmt(e, f),
 			Self::End(e) => Debug::fmt(e, f),
 			Self::Group(e) => Debug::fmt(e, f),
+			Self::Raw(e) => Debug::fmt(e, f),
+			Self::Doc(e) => Debug::fmt(e, f),
 		}
 	}
 }
@@ -242,8 +344,25 @@ impl Debug for Ident {
 #[derive(Clone)]
 pub struct Str {
 	pub value: String,
+	// where each char of the value is in the source, an escape sequence is a single char
+	pub spans: Vec<(usize, usize)>,
 	pub scope: SrcScope,
 }
+impl Str {
+	// the source of the chars `start..end` of the value,
+	// the closing quote if they're past the end
+	pub fn value_scope(&self, start:usize, end:usize) -> SrcScope {
+		let from = match self.spans.get(start) {
+			Some((from, _)) => *from,
+			None => self.spans.last().map_or(self.scope.start+1, |(_, to)| *to),
+		};
+		let to = match self.spans.get(end.wrapping_sub(1)) {
+			Some((_, to)) if end > start => *to,
+			_ => from+1,
+		};
+		SrcScope { start: from, end: to, file: self.scope.file.clone() }
+	}
+}
 impl Debug for Str {
 	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
 		f.write_str("Str(")?;
@@ -265,6 +384,76 @@ impl Debug for Int {
 	}
 }
 
+// `1b`
+#[derive(Clone)]
+pub struct Byte {
+	pub value: i8,
+	pub scope: SrcScope,
+}
+impl Debug for Byte {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Byte(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
+// `1s`
+#[derive(Clone)]
+pub struct Short {
+	pub value: i16,
+	pub scope: SrcScope,
+}
+impl Debug for Short {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Short(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
+// `1L`
+#[derive(Clone)]
+pub struct Long {
+	pub value: i64,
+	pub scope: SrcScope,
+}
+impl Debug for Long {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Long(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
+// `1.5f`
+#[derive(Clone)]
+pub struct Float {
+	pub value: f32,
+	pub scope: SrcScope,
+}
+impl Debug for Float {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Float(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
+// `1.5d` or `1.5`
+#[derive(Clone)]
+pub struct Double {
+	pub value: f64,
+	pub scope: SrcScope,
+}
+impl Debug for Double {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Double(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
 #[derive(Clone)]
 pub struct Sep {
 	pub value: char,
@@ -278,6 +467,49 @@ impl Debug for Sep {
 	}
 }
 
+// `@a`, `@e[type=cow,limit=1]`, ...
+#[derive(Clone)]
+pub struct Selector {
+	pub base: char,
+	pub args: Vec<SelectorArg>,
+	pub scope: SrcScope,
+}
+impl Selector {
+	// whether every entity this selects is a player
+	pub fn players(&self) -> bool {
+		matches!(self.base, 'a' | 'p' | 'r') || self.args.iter().any(|arg| {
+			arg.key == "type" && !arg.negated
+				&& (arg.value == "player" || arg.value == "minecraft:player")
+		})
+	}
+}
+impl Display for Selector {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "@{}", self.base)?;
+		if self.args.is_empty() { return Ok(()) }
+		let args:Vec<String> = self.args.iter().map(|arg| format!(
+			"{}={}{}", arg.key, if arg.negated { "!" } else { "" }, arg.value
+		)).collect();
+		write!(f, "[{}]", args.join(","))
+	}
+}
+impl Debug for Selector {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Selector(")?;
+		Debug::fmt(&self.to_string(), f)?;
+		f.write_str(")")
+	}
+}
+// `key=value` or `key=!value`, the value is kept as it's written
+#[derive(Clone, Debug)]
+pub struct SelectorArg {
+	pub key: String,
+	pub key_scope: SrcScope,
+	pub negated: bool,
+	pub value: String,
+	pub value_scope: SrcScope,
+}
+
 #[derive(Clone)]
 pub struct Symbol {
 	pub value: String,
@@ -301,6 +533,34 @@ impl Debug for End {
 	}
 }
 
+// the exact source of a command in an `unsafe` block
+#[derive(Clone)]
+pub struct Raw {
+	pub value: String,
+	pub scope: SrcScope,
+}
+impl Debug for Raw {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Raw(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
+// a `///` comment, without the slashes
+#[derive(Clone)]
+pub struct Doc {
+	pub value: String,
+	pub scope: SrcScope,
+}
+impl Debug for Doc {
+	fn fmt(&self, f:&mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Doc(")?;
+		Debug::fmt(&self.value, f)?;
+		f.write_str(")")
+	}
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Delimiter {
 	Parentheses,
@@ -351,7 +611,7 @@ impl Debug for Group {
 	}
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct SrcScope {
 	pub start: usize,
 	pub end: usize,
@@ -364,7 +624,7 @@ impl SrcScope {
 		SrcScope {
 			start: self.start,
 			end: other.end,
-			file: self.file,
+			file: self.file.clone(),
 		}
 	}
 
@@ -388,4 +648,14 @@ impl SrcScope {
 	pub fn len(&self) -> usize {
 		self.end-self.start
 	}
+	// the source code in this scope
+	pub fn text(&self) -> String {
+		self.file.source().chars()
+			.skip(self.start)
+			.take(self.len())
+			.collect()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.end == self.start
+	}
 }
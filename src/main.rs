@@ -1,31 +1,26 @@
-#![feature(let_else)]
 #![allow(dead_code)]
 
-use lexer::files;
-use lexer::Lexer;
-use parser::Parser;
+use std::path::Path;
+use compiler::Compiler;
+use lexer::files::SourceMap;
 use crate::debug::errors::CompileErr;
-use crate::debug::NewDebugTree;
 
 pub mod debug;
 pub mod compiler;
 pub mod lexer;
 pub mod parser;
+pub mod project;
 
 fn main() -> Result<(), CompileErr> {
-	files::init();
-	files::load_file("datapack.mccs");
+	let mut sources = SourceMap::new();
+	let dir = Path::new(".");
+	let packs = project::load(&mut sources, dir);
 
-	let mut lexer = Lexer::new(files::ref_file(0));
-	let tokens = lexer.lex();
-
-	// for t in &tokens {
-	// 	println!("{:?}", t);
-	// }
-
-	let mut parser = Parser::new(tokens);
-	let datapack = parser.parse();
-	println!("{:?}", datapack.debug_tree());
+	let mut files = Vec::new();
+	for datapack in &packs {
+		files.extend(Compiler::new(datapack, &packs).compile());
+	}
+	compiler::write_files(&dir.join(compiler::OUT_DIR), &files);
 
 	Ok(())
 }
@@ -1,15 +1,24 @@
 use std::fmt::{Debug};
-use crate::debug::errors::{Help, CompileResult, ErrorInfo, error};
+use crate::debug::errors::{Help, CompileResult, ErrorInfo, error, warning};
 use crate::lexer::token_stream::{TokenIter, TokenList};
-use crate::lexer::tokens::{Delimiter, Group, Ident, Sep, SrcScope, Symbol, Token};
+use crate::lexer::tokens::{Delimiter, Doc, Group, Ident, Int, Raw, Sep, SrcScope, Str, Symbol, Token};
 
 pub struct Parser {
 	pub tokens: TokenIter,
+	// the path of the module the tokens are from, empty for the root of the datapack
+	pub module: String,
 }
 impl Parser {
 	pub fn new(tokens:Vec<Token>) -> Self {
 		Parser {
 			tokens: TokenIter::new(tokens),
+			module: String::new(),
+		}
+	}
+	pub fn for_module(tokens:Vec<Token>, module:&str) -> Self {
+		Parser {
+			tokens: TokenIter::new(tokens),
+			module: module.to_owned(),
 		}
 	}
 	pub fn parse(&mut self) -> Datapack {
@@ -38,9 +47,21 @@ impl Parser {
 		).fatal();
 
 		let mut datapack = Datapack::empty(name);
+		self.parse_module(&mut datapack);
+		datapack
+	}
 
+	// parses the items of a file into the datapack
+	pub fn parse_module(&mut self, datapack:&mut Datapack) {
+		// the doc comments before the next item
+		let mut docs:Vec<Doc> = Vec::new();
 		let mut next = self.tokens.next().clone();
 		while !next.is_end() {
+			if let Some(doc) = next.as_doc() {
+				docs.push(doc.clone());
+				next = self.tokens.next().clone();
+				continue;
+			}
 			let ident = next.expect_ident(
 				Some("top-level"),
 				None,
@@ -48,17 +69,55 @@ impl Parser {
 
 			match ident.value.as_str() {
 				"function" => {
-					let function = self.parse_function(ident);
+					let function = self.parse_function(ident, std::mem::take(&mut docs));
 					datapack.functions.push(function);
 				},
 				"scoreboard" => {
-					let scoreboard = self.parse_scoreboard(ident);
+					let scoreboard = self.parse_scoreboard(ident, std::mem::take(&mut docs));
 					datapack.scoreboards.push(scoreboard);
 				},
 				"inline" => {
-					let inline = self.parse_inline(ident);
+					let inline = self.parse_inline(ident, std::mem::take(&mut docs));
 					datapack.inlines.push(inline);
 				}
+				"loop_limit" => {
+					unused_docs(&std::mem::take(&mut docs));
+					let limit = self.parse_loop_limit(ident);
+					if let Some(existing) = &datapack.loop_limit {
+						error(limit.ident.scope, ErrorInfo {
+							cause: "duplicate loop limit",
+							pointer: "the loop limit has already been set",
+							context: Some("loop limit declaration"),
+							help: None,
+						}).note(existing.ident.scope.clone(), "set here").fatal();
+					}
+					datapack.loop_limit = Some(limit);
+				}
+				"external" => {
+					unused_docs(&std::mem::take(&mut docs));
+					let external = self.parse_macro(ident);
+					if let Some(existing) = datapack.macros.iter()
+						.find(|existing| existing.name.value == external.name.value) {
+						error(external.name.scope, ErrorInfo {
+							cause: "duplicate macro",
+							pointer: "a macro with this name has already been declared",
+							context: Some("macro declaration"),
+							help: None,
+						}).note(existing.name.scope.clone(), "declared here").fatal();
+					}
+					datapack.macros.push(external);
+				}
+				"include" => {
+					unused_docs(&std::mem::take(&mut docs));
+					let include = self.parse_include(ident);
+					datapack.includes.push(include);
+				}
+				"datapack" => error(ident.scope, ErrorInfo {
+					cause: "duplicate datapack declaration",
+					pointer: "the datapack is only declared at the top of `datapack.mccs`",
+					context: Some("top-level"),
+					help: None,
+				}).note(datapack.name.scope.clone(), "declared here").fatal(),
 				_ => error(ident.scope, ErrorInfo {
 					cause: ident.value.as_str(),
 					pointer: "unknown item declaration",
@@ -68,10 +127,84 @@ impl Parser {
 			}
 			next = self.tokens.next().clone();
 		}
-		datapack
+		unused_docs(&docs);
+	}
+
+	pub fn parse_macro(&mut self, keyword:Ident) -> Macro {
+		let help = || Some(Help {
+			msg: "give the path to the executable that expands the macro".to_owned(),
+			source: "external macro print = \"some_path/print-macro.exe\";".to_owned(),
+			line: 0,
+		});
+		self.tokens.next().expect_ident_w(
+			"macro",
+			Some("macro declaration"),
+			help(),
+		).fatal();
+		let name = self.tokens.next().expect_ident(
+			Some("macro declaration"),
+			help(),
+		).fatal();
+		self.tokens.next().expect_symbol_w(
+			"=",
+			Some("macro declaration"),
+			help(),
+		).fatal();
+		let path = self.tokens.next().expect_string(
+			Some("macro declaration"),
+			help(),
+		).fatal();
+		let semi_colon = self.tokens.next().expect_sep_w(
+			';',
+			Some("macro declaration"),
+			None,
+		).fatal();
+		Macro { keyword, name, path, semi_colon: semi_colon.scope }
+	}
+
+	pub fn parse_include(&mut self, keyword:Ident) -> Include {
+		let path = self.tokens.next().expect_string(
+			Some("include"),
+			Some(Help {
+				msg: "give the path to the directory of the other datapack".to_owned(),
+				source: "include \"../other_pack\";".to_owned(),
+				line: 0,
+			}),
+		).fatal();
+		let semi_colon = self.tokens.next().expect_sep_w(
+			';',
+			Some("include"),
+			None,
+		).fatal();
+		Include { keyword, path, semi_colon: semi_colon.scope, namespace: None }
+	}
+
+	pub fn parse_loop_limit(&mut self, ident:Ident) -> LoopLimit {
+		let value = self.tokens.next().expect_int(
+			Some("loop limit declaration"),
+			Some(Help {
+				msg: "the number of iterations a loop can run each tick, or 0 for no limit".to_owned(),
+				source: "loop_limit 1000;".to_owned(),
+				line: 0,
+			}),
+		).fatal();
+		if value.value < 0 {
+			error(value.scope, ErrorInfo {
+				cause: "invalid loop limit",
+				pointer: "expected the number of iterations, or 0 for no limit",
+				context: Some("loop limit declaration"),
+				help: None,
+			}).fatal();
+		}
+		self.tokens.next().expect_sep_w(
+			';',
+			Some("loop limit declaration"),
+			None,
+		).fatal();
+		LoopLimit { ident, value }
 	}
 
-	pub fn parse_inline(&mut self, keyword:Ident) -> Inline {
+	pub fn parse_inline(&mut self, keyword:Ident, docs:Vec<Doc>) -> Inline {
 		// get name
 		let name = self.tokens.next().expect_ident(
 			Some("inline declaration"), None,
@@ -103,6 +236,14 @@ impl Parser {
 			let ty = InlineArgType::parse(
 				&ty_ident, Some("inline argument declaration"), None,
 			).fatal();
+			if let Some(params) = iter.current().as_symbol_w("<") {
+				error(params.scope.clone(), ErrorInfo {
+					cause: "invalid type",
+					pointer: format!("{} doesn't take type parameters", ty.describe()).as_str(),
+					context: Some("inline argument declaration"),
+					help: None,
+				}).fatal();
+			}
 
 			// consume separator
 			let next = iter.next();
@@ -132,18 +273,22 @@ impl Parser {
 			Delimiter::CurlyBrackets,
 			Some("inline declaration"), None
 		).fatal();
+		let body = Self::parse_block(&block);
 
 		// done
 		Inline {
+			docs,
+			module: self.module.clone(),
 			keyword,
 			name,
 			args,
 			parens: group.scope,
-			block
+			block,
+			body,
 		}
 	}
 
-	pub fn parse_scoreboard(&mut self, keyword:Ident) -> Scoreboard {
+	pub fn parse_scoreboard(&mut self, keyword:Ident, docs:Vec<Doc>) -> Scoreboard {
 		let objective = self.tokens.next().expect_ident(
 			Some("scoreboard declaration"),
 			None,
@@ -157,10 +302,10 @@ impl Parser {
 			Some("scoreboard declaration"),
 			None,
 		).fatal().scope;
-		Scoreboard { keyword, objective, name, semi_colon }
+		Scoreboard { docs, keyword, objective, name, semi_colon }
 	}
 
-	pub fn parse_function(&mut self, keyword:Ident) -> Function {
+	pub fn parse_function(&mut self, keyword:Ident, docs:Vec<Doc>) -> Function {
 		let name = self.tokens.next().expect_ident(
 			Some("function declaration"),
 			None,
@@ -184,7 +329,7 @@ impl Parser {
 			if let Some(ident) = token.as_ident() {
 				if colon.is_some() {
 					if objective.is_some() {
-						error(ident.scope, ErrorInfo {
+						error(ident.scope.clone(), ErrorInfo {
 							cause: "illegal identifier, only 1 identifier after the colon",
 							pointer: "an objective has already been defined",
 							context: Some("function args declaration"),
@@ -198,10 +343,10 @@ impl Parser {
 				} else { input.push(ident.clone()); }
 			}
 			if let Some(symbol) = token.as_symbol_w("->") {
-				arrow = Some(symbol.scope);
+				arrow = Some(symbol.scope.clone());
 			}
 			if let Some(sep) = token.as_sep_w(':') {
-				colon = Some(sep.scope);
+				colon = Some(sep.scope.clone());
 			}
 		});
 		let args = FunArgs {
@@ -214,16 +359,19 @@ impl Parser {
 		let mut next = self.tokens.next().clone();
 		if let Some(ident) = next.as_ident_w("at") {
 			// parse at mod
-			let loc_token = self.tokens.next();
+			let loc_ident = self.tokens.next().expect_ident(
+				Some("function declaration"),
+				None,
+			).fatal();
 			let loc = AtLoc::parse(
-				ident,
+				&loc_ident,
 				Some("function declaration"),
 				None
 			).fatal();
 			at = Some(At {
 				ident: ident.clone(),
 				loc,
-				loc_scope: loc_token.as_ident().unwrap().scope
+				loc_scope: loc_ident.scope
 			});
 			next = self.tokens.next().clone();
 		}
@@ -233,80 +381,603 @@ impl Parser {
 			Some("function declaration"),
 			None
 		).fatal();
+		let body = Self::parse_block(&block);
 
 		Function {
-			keyword, name, args, block, at
+			docs, module: self.module.clone(), keyword, name, args, block, body, at
 		}
 	}
 
-	pub fn parse_function_block(block:&Group) -> Vec<Statement> {
+	pub fn parse_block(block:&Group) -> Vec<Statement> {
+		let mut parser = Parser::new(block.tokens.clone());
 		let mut statements:Vec<Statement> = Vec::new();
 
-		block.tokens.iter_tokens(|token, iter| {
-			if let Some(ident) = token.as_ident_w("unsafe") {
-				let block = iter.next().expect_group_w(
-					Delimiter::CurlyBrackets,
-					Some("unsafe block declaration"),
-					None,
-				).fatal();
-				let mut commands = Vec::new();
-				let mut command = UnsafeCmd::new();
-
-				block.tokens.iter_tokens(|token, _iter| {
-					if token.as_sep_w(';').is_some() {
-						if !command.tokens.is_empty() {
-							commands.push(command.clone());
-							command = UnsafeCmd::new();
-						}
-					} else {
-						command.tokens.push(token.clone());
-					}
-				});
+		let mut docs:Vec<Doc> = Vec::new();
+		let mut next = parser.tokens.next().clone();
+		while !next.is_end() {
+			if let Some(doc) = next.as_doc() {
+				docs.push(doc.clone());
+				next = parser.tokens.next().clone();
+				continue;
+			}
+			match parser.parse_statement(next) {
+				// scoreboards are the only statements that can be documented
+				Some(Statement::Scoreboard(mut scoreboard)) => {
+					scoreboard.docs = std::mem::take(&mut docs);
+					statements.push(Statement::Scoreboard(scoreboard));
+				}
+				Some(statement) => {
+					unused_docs(&std::mem::take(&mut docs));
+					statements.push(statement);
+				}
+				None => {}
+			}
+			next = parser.tokens.next().clone();
+		}
+		unused_docs(&docs);
+		statements
+	}
 
-				statements.push(Statement::Unsafe(Unsafe {
-					ident: ident.clone(),
-					block,
-					commands,
-				}))
+	pub fn parse_statement(&mut self, token:Token) -> Option<Statement> {
+		// stray semi-colons between statements are allowed
+		if token.as_sep_w(';').is_some() {
+			return None;
+		}
+		if let Some(ident) = token.as_ident_w("unsafe") {
+			let unsafe_block = self.parse_unsafe(ident.clone());
+			return Some(Statement::Unsafe(unsafe_block));
+		}
+		if let Some(ident) = token.as_ident_w("scoreboard") {
+			let scoreboard = self.parse_scoreboard(ident.clone(), Vec::new());
+			return Some(Statement::Scoreboard(scoreboard));
+		}
+		if let Some(ident) = token.as_ident_w("let") {
+			let local = self.parse_let(ident.clone());
+			return Some(Statement::Let(local));
+		}
+		if let Some(ident) = token.as_ident_w("at") {
+			let at = self.parse_at(ident.clone());
+			return Some(Statement::At(at));
+		}
+		if let Some(ident) = token.as_ident_w("if") {
+			let if_statement = self.parse_if(ident.clone());
+			return Some(Statement::If(Box::new(if_statement)));
+		}
+		if let Some(ident) = token.as_ident_w("while") {
+			let while_loop = self.parse_while(ident.clone());
+			return Some(Statement::While(Box::new(while_loop)));
+		}
+		if let Some(ident) = token.as_ident_w("for") {
+			let for_loop = self.parse_for(ident.clone());
+			return Some(Statement::For(Box::new(for_loop)));
+		}
+		if let Some(symbol) = token.as_sep_w('|') {
+			return Some(self.parse_pipe(symbol.clone()));
+		}
+		if let (Some(name), Some(bang)) = (token.as_ident(), self.tokens.current().as_symbol_w("!")) {
+			let (name, bang) = (name.clone(), bang.clone());
+			self.tokens.next();
+			let expansion = self.parse_macro_call(name, bang);
+			return Some(Statement::Macro(expansion));
+		}
+		if token.is_ident() && self.at_call() {
+			let call = self.parse_call();
+			self.tokens.next().expect_sep_w(
+				';',
+				Some("function call"),
+				None,
+			).fatal();
+			return Some(Statement::Call(call));
+		}
+		if token.is_ident() || token.is_selector() {
+			let mut target = Arg::new();
+			target.tokens.push(token);
+			return Some(Statement::Assign(self.parse_assign(target)));
+		}
+		error(token.scope(), ErrorInfo {
+			cause: "invalid statement",
+			pointer: "expected `unsafe`, `scoreboard`, `let`, `if`, `while`, `for`, `at`, a function call, a macro call or an assignment",
+			context: Some("function block"),
+			help: None,
+		}).fatal()
+	}
+
+	pub fn parse_unsafe(&mut self, ident:Ident) -> Unsafe {
+		let block = self.tokens.next().expect_group_w(
+			Delimiter::CurlyBrackets,
+			Some("unsafe block declaration"),
+			None,
+		).fatal();
+		let mut commands:Vec<UnsafeCmd> = Vec::new();
+		block.tokens.iter_tokens(|token, _iter| {
+			if let Some(raw) = token.as_raw() {
+				commands.push(UnsafeCmd { raw: raw.clone(), sep: None });
 			}
+			if let Some(sep) = token.as_sep_w(';') {
+				if let Some(command) = commands.last_mut() {
+					command.sep.get_or_insert(sep.clone());
+				}
+			}
+		});
 
-			if let Some(path) = iter.get_path(Some("")) {
-				let group = iter.next().expect_group_w(
-					Delimiter::Parentheses,
-					Some("function call declaration"),
-					None,
-				).fatal();
-				let mut args = Vec::new();
-				let mut arg = Arg::new();
-				group.tokens.iter_tokens(|token, _iter| {
-					if let Some(sep) = token.as_sep_w(',') {
-						assert!(!arg.tokens.is_empty());
-						arg.sep = Some(sep.clone());
-						args.push(arg.clone());
-						arg = Arg::new();
-					}
-					arg.tokens.push(token);
-				});
+		Unsafe { ident, block, commands }
+	}
 
-				statements.push(Statement::Call(Call {
-					path, group, args,
-				}));
+	pub fn parse_at(&mut self, ident:Ident) -> AtBlock {
+		// everything up to the block is the target
+		let mut target = Arg::new();
+		while !self.tokens.current().is_end()
+			&& self.tokens.current().as_group_w(Delimiter::CurlyBrackets).is_none() {
+			target.tokens.push(self.tokens.next().clone());
+		}
+		if target.tokens.is_empty() {
+			error(self.tokens.current().scope(), ErrorInfo {
+				cause: "missing target",
+				pointer: "expected a target selector before the block",
+				context: Some("at block"),
+				help: Some(Help {
+					msg: "`at` runs its block as and at every entity the selector matches".to_owned(),
+					source: "at @a[tag=winner] { give_reward(); }".to_owned(),
+					line: 0,
+				}),
+			}).fatal();
+		}
+		let block = self.tokens.next().expect_group_w(
+			Delimiter::CurlyBrackets,
+			Some("at block"),
+			None,
+		).fatal();
+		let body = Self::parse_block(&block);
+
+		AtBlock { ident, target, block, body }
+	}
+
+	pub fn parse_if(&mut self, ident:Ident) -> If {
+		let condition = self.parse_expr("condition");
+		let block = self.tokens.next().expect_group_w(
+			Delimiter::CurlyBrackets,
+			Some("if statement"),
+			None,
+		).fatal();
+		let body = Self::parse_block(&block);
+
+		let mut otherwise = None;
+		if let Some(else_ident) = self.tokens.current().as_ident_w("else") {
+			let else_ident = else_ident.clone();
+			self.tokens.next();
+			// `else if` is an else block with only an if statement in it
+			let body = match self.tokens.current().as_ident_w("if") {
+				Some(if_ident) => {
+					let if_ident = if_ident.clone();
+					self.tokens.next();
+					vec![Statement::If(Box::new(self.parse_if(if_ident)))]
+				}
+				None => {
+					let block = self.tokens.next().expect_group_w(
+						Delimiter::CurlyBrackets,
+						Some("else statement"),
+						None,
+					).fatal();
+					Self::parse_block(&block)
+				}
+			};
+			otherwise = Some(Else { ident: else_ident, body });
+		}
+
+		If { ident, condition, block, body, otherwise }
+	}
+
+	pub fn parse_while(&mut self, ident:Ident) -> While {
+		let condition = self.parse_expr("condition");
+		let block = self.tokens.next().expect_group_w(
+			Delimiter::CurlyBrackets,
+			Some("while loop"),
+			None,
+		).fatal();
+		let body = Self::parse_block(&block);
+
+		While { ident, condition, block, body }
+	}
+
+	pub fn parse_for(&mut self, ident:Ident) -> For {
+		let var = self.tokens.next().expect_ident(
+			Some("for loop"),
+			None,
+		).fatal();
+		self.tokens.next().expect_ident_w(
+			"in",
+			Some("for loop"),
+			Some(Help {
+				msg: "a for loop counts through a range".to_owned(),
+				source: "for i in 0..10 {}".to_owned(),
+				line: 0,
+			}),
+		).fatal();
+		// the loop needs both ends of its range, unlike the ranges in conditions
+		if let Some(dots) = self.tokens.current().as_symbol_w("..") {
+			error(dots.scope.clone(), ErrorInfo {
+				cause: "missing range start",
+				pointer: "expected the value the loop starts counting from",
+				context: Some("for loop range"),
+				help: None,
+			}).fatal();
+		}
+		let start = self.parse_expr("for loop range");
+		let dots = self.tokens.next().expect_symbol_w(
+			"..",
+			Some("for loop range"),
+			None,
+		).fatal();
+		if self.tokens.current().as_group_w(Delimiter::CurlyBrackets).is_some() {
+			error(dots.scope, ErrorInfo {
+				cause: "missing range end",
+				pointer: "expected the value the loop stops before after this",
+				context: Some("for loop range"),
+				help: None,
+			}).fatal();
+		}
+		let end = self.parse_expr("for loop range");
+		let block = self.tokens.next().expect_group_w(
+			Delimiter::CurlyBrackets,
+			Some("for loop"),
+			None,
+		).fatal();
+		let body = Self::parse_block(&block);
+
+		For { ident, var, start, dots, end, block, body }
+	}
+
+	pub fn parse_macro_call(&mut self, name:Ident, bang:Symbol) -> MacroCall {
+		let group = self.tokens.next().expect_group_w(
+			Delimiter::Parentheses,
+			Some("macro call"),
+			Some(Help {
+				msg: "give the macro its input in parentheses".to_owned(),
+				source: format!("{}!(\"some input\");", name.value),
+				line: 0,
+			}),
+		).fatal();
+		self.tokens.next().expect_sep_w(
+			';',
+			Some("macro call"),
+			None,
+		).fatal();
+		MacroCall { name, bang, group }
+	}
+
+	// whether the previous identifier starts a function call
+	fn at_call(&self) -> bool {
+		let start = self.tokens.index-1;
+		let mut i = self.tokens.index;
+		while i < self.tokens.tokens.len() && ResLoc::continues(&self.tokens.tokens[start..i], &self.tokens.tokens[i]) {
+			i += 1;
+		}
+		self.tokens.tokens[i].as_group_w(Delimiter::Parentheses).is_some()
+	}
+
+	pub fn parse_let(&mut self, ident:Ident) -> Let {
+		let name = self.tokens.next().expect_ident(
+			Some("local declaration"),
+			None,
+		).fatal();
+		let equals = self.tokens.next().expect_symbol_w(
+			"=",
+			Some("local declaration"),
+			Some(Help {
+				msg: "a local needs a value when it's declared".to_owned(),
+				source: "let x = 5;".to_owned(),
+				line: 0,
+			}),
+		).fatal();
+		let value = self.parse_expr("local declaration");
+		self.tokens.next().expect_sep_w(
+			';',
+			Some("local declaration"),
+			None,
+		).fatal();
+		Let { ident, name, equals, value }
+	}
+
+	// `<target> = <expr>;`, or with an operator like `+=`, the first token of the target is already consumed
+	pub fn parse_assign(&mut self, target:Arg) -> Assign {
+		let mut target = target;
+		while !self.tokens.current().is_end() && !self.tokens.current().is_symbol()
+			&& self.tokens.current().as_sep_w(';').is_none() {
+			target.tokens.push(self.tokens.next().clone());
+		}
+		let Some(symbol) = self.tokens.next().as_symbol().cloned() else {
+			error(self.tokens.prev().unwrap().scope(), ErrorInfo {
+				cause: "invalid statement",
+				pointer: "expected `=` or the arguments of a function call",
+				context: Some("assignment"),
+				help: None,
+			}).fatal()
+		};
+		let op = match symbol.value.as_str() {
+			"=" => None,
+			value => match Operator::parse_assign(value) {
+				Some(op) => Some(op),
+				None => error(symbol.scope, ErrorInfo {
+					cause: "invalid assignment",
+					pointer: "expected `=`, `+=`, `-=`, `*=`, `/=` or `%=`",
+					context: Some("assignment"),
+					help: None,
+				}).fatal(),
 			}
+		};
+		let value = self.parse_expr("assignment");
+		self.tokens.next().expect_sep_w(
+			';',
+			Some("assignment"),
+			None,
+		).fatal();
+		Assign { target, symbol, op, value }
+	}
 
-			if let Some(_sep) = token.as_sep_w('|') {
+	// expressions end at a block, a `;`, a `,`, a `..` or the end of the tokens
+	pub fn parse_expr(&mut self, context:&str) -> Expr {
+		let lhs = self.parse_sum(context);
+		let expr = if let Some(ident) = self.tokens.current().as_ident_w("in").cloned() {
+			self.tokens.next();
+			let range = self.parse_range(context);
+			Expr::In(Box::new(InRange { lhs, ident, range }))
+		} else {
+			let Some(symbol) = self.tokens.current().as_symbol().cloned() else { return lhs };
+			let Some(cmp) = Comparison::parse(&symbol.value) else { return lhs };
+			self.tokens.next();
+			let rhs = self.parse_sum(context);
+			Expr::Compare(Box::new(Compare { lhs, cmp, symbol, rhs }))
+		};
+		if let Some(next) = self.tokens.current().as_symbol() {
+			if Comparison::parse(&next.value).is_some() {
+				error(next.scope.clone(), ErrorInfo {
+					cause: "chained comparison",
+					pointer: "comparisons can't be chained",
+					context: Some(context),
+					help: None,
+				}).fatal();
+			}
+		}
+		expr
+	}
 
+	// `<min>..<max>` with integer bounds that are both included, either of them can be left out.
+	// the range becomes `matches <range>`, so the bounds can't be expressions
+	pub fn parse_range(&mut self, context:&str) -> Range {
+		let min = self.range_bound(context);
+		let dots = self.tokens.next().expect_symbol_w(
+			"..",
+			Some(context),
+			Some(range_help()),
+		).fatal();
+		let max = self.range_bound(context);
+		// a bound followed by an operator is the start of an expression
+		if let Some(symbol) = self.tokens.current().as_symbol() {
+			if matches!(symbol.value.as_str(), "+" | "-" | "*" | "/" | "%") {
+				error(symbol.scope.clone(), ErrorInfo {
+					cause: "invalid range",
+					pointer: "the bounds of a range can only be integers",
+					context: Some(context),
+					help: Some(range_help()),
+				}).fatal();
 			}
-		});
-		statements
+		}
+		let range = Range { min, dots, max };
+		match (&range.min, &range.max) {
+			(None, None) => error(range.dots.scope, ErrorInfo {
+				cause: "invalid range",
+				pointer: "expected an integer before or after the dots",
+				context: Some(context),
+				help: None,
+			}).fatal(),
+			(Some(min), Some(max)) if min.value > max.value => error(range.scope(), ErrorInfo {
+				cause: "empty range",
+				pointer: "the minimum of this range is more than its maximum",
+				context: Some(context),
+				help: None,
+			}).fatal(),
+			_ => range,
+		}
+	}
+	// an integer, or nothing when the bound is left out
+	fn range_bound(&mut self, context:&str) -> Option<Int> {
+		let token = self.tokens.current();
+		if let Some(int) = token.as_int().cloned() {
+			self.tokens.next();
+			return Some(int);
+		}
+		if token.is_ident() || token.is_number() || token.as_group_w(Delimiter::Parentheses).is_some() {
+			error(token.scope(), ErrorInfo {
+				cause: "invalid range",
+				pointer: "the bounds of a range can only be integers",
+				context: Some(context),
+				help: Some(range_help()),
+			}).fatal();
+		}
+		None
+	}
+	fn parse_sum(&mut self, context:&str) -> Expr {
+		let mut lhs = self.parse_product(context);
+		while let Some(symbol) = self.tokens.current().as_symbol().cloned() {
+			let op = match symbol.value.as_str() {
+				"+" => Operator::Add,
+				"-" => Operator::Sub,
+				_ => break,
+			};
+			self.tokens.next();
+			let rhs = self.parse_product(context);
+			lhs = Expr::Binary(Box::new(Binary { lhs, op, symbol, rhs }));
+		}
+		lhs
+	}
+	fn parse_product(&mut self, context:&str) -> Expr {
+		let mut lhs = self.parse_unary(context);
+		while let Some(symbol) = self.tokens.current().as_symbol().cloned() {
+			let op = match symbol.value.as_str() {
+				"*" => Operator::Mul,
+				"/" => Operator::Div,
+				"%" => Operator::Rem,
+				_ => break,
+			};
+			self.tokens.next();
+			let rhs = self.parse_unary(context);
+			lhs = Expr::Binary(Box::new(Binary { lhs, op, symbol, rhs }));
+		}
+		lhs
+	}
+	fn parse_unary(&mut self, context:&str) -> Expr {
+		if let Some(symbol) = self.tokens.current().as_symbol_w("-") {
+			let symbol = symbol.clone();
+			self.tokens.next();
+			let expr = self.parse_unary(context);
+			return Expr::Neg(symbol, Box::new(expr));
+		}
+		self.parse_primary(context)
+	}
+	// a value, a function call or an expression in parentheses
+	fn parse_primary(&mut self, context:&str) -> Expr {
+		let first = self.tokens.next().clone();
+		if let Some(group) = first.as_group_w(Delimiter::Parentheses) {
+			let mut parser = Parser::new(group.tokens.clone());
+			let expr = parser.parse_expr(context);
+			let next = parser.tokens.next();
+			if !next.is_end() {
+				error(next.scope(), ErrorInfo {
+					cause: "invalid expression",
+					pointer: "expected an operator or the closing parenthesis",
+					context: Some(context),
+					help: None,
+				}).fatal();
+			}
+			return Expr::Paren(group.clone(), Box::new(expr));
+		}
+		if first.is_ident() && self.at_call() {
+			return Expr::Call(self.parse_call());
+		}
+		let mut arg = Arg::new();
+		let mut next = first;
+		loop {
+			let ends = next.is_end() || next.is_symbol()
+				|| next.as_group_w(Delimiter::CurlyBrackets).is_some()
+				|| next.as_sep_w(';').is_some() || next.as_sep_w(',').is_some();
+			if ends {
+				error(next.scope(), ErrorInfo {
+					cause: "missing value",
+					pointer: "expected an integer, a pipe, a score, a function call or an expression in parentheses",
+					context: Some(context),
+					help: None,
+				}).fatal();
+			}
+			arg.tokens.push(next);
+			// `-`, `.` and `/` after a namespace are part of a resource location, like `minecraft:oak-log`
+			let namespaced = |tokens:&[Token]| {
+				tokens[0].is_ident() && tokens.iter().any(|token| token.as_sep_w(':').is_some())
+			};
+			while namespaced(&arg.tokens) && ResLoc::continues(&arg.tokens, self.tokens.current()) {
+				arg.tokens.push(self.tokens.next().clone());
+			}
+			let current = self.tokens.current();
+			let ends = current.is_end() || current.is_symbol() || current.as_ident_w("in").is_some()
+				|| current.as_group_w(Delimiter::CurlyBrackets).is_some()
+				|| current.as_sep_w(';').is_some() || current.as_sep_w(',').is_some();
+			if ends { break }
+			next = self.tokens.next().clone();
+		}
+		Expr::Value(arg)
+	}
+
+	// `|name = <expr>;` declares a pipe, other assignments change one that exists
+	pub fn parse_pipe(&mut self, symbol:Sep) -> Statement {
+		let name = self.tokens.next().expect_ident(
+			Some("pipe declaration"),
+			Some(Help {
+				msg: "a pipe holds the value of an expression, like the return value of a function call".to_owned(),
+				source: "|sum = add(a, b);".to_owned(),
+				line: 0,
+			}),
+		).fatal();
+		if self.tokens.current().as_symbol_w("=").is_none() {
+			let mut target = Arg::new();
+			target.tokens.push(Token::Sep(symbol));
+			target.tokens.push(Token::Ident(name));
+			return Statement::Assign(self.parse_assign(target));
+		}
+		let equals = self.tokens.next().as_symbol().unwrap().clone();
+		let value = self.parse_expr("pipe declaration");
+		self.tokens.next().expect_sep_w(
+			';',
+			Some("pipe declaration"),
+			None,
+		).fatal();
+		Statement::Pipe(Pipe { symbol, name, equals, value })
+	}
+
+	// expects the first ident of the path to be the previous token
+	pub fn parse_call(&mut self) -> Call {
+		let path = self.tokens.get_res_loc("function call").unwrap();
+		let group = self.tokens.next().expect_group_w(
+			Delimiter::Parentheses,
+			Some("function call"),
+			None,
+		).fatal();
+
+		// every argument is an expression, followed by a comma or the end of the parentheses
+		let mut args = Vec::new();
+		let mut parser = Parser::new(group.tokens.clone());
+		while !parser.tokens.current().is_end() {
+			if let Some(sep) = parser.tokens.current().as_sep_w(',') {
+				error(sep.scope.clone(), ErrorInfo {
+					cause: "missing argument",
+					pointer: "expected an argument before this comma",
+					context: Some("function call"),
+					help: None,
+				}).fatal();
+			}
+			args.push(parser.parse_expr("function call"));
+			let next = parser.tokens.next();
+			if next.is_end() { break }
+			if next.as_sep_w(',').is_none() {
+				error(next.scope(), ErrorInfo {
+					cause: "invalid argument",
+					pointer: "expected an operator, a comma or the closing parenthesis",
+					context: Some("function call"),
+					help: None,
+				}).fatal();
+			}
+		}
+
+		Call { path, group, args }
 	}
 }
 
+fn range_help() -> Help {
+	Help {
+		msg: "a range is written as `<min>..<max>`, where the bounds are integers and either can be left out".to_owned(),
+		source: "if x in 1..5 {}".to_owned(),
+		line: 0,
+	}
+}
+
+// doc comments only document functions, inlines and scoreboards
+fn unused_docs(docs:&[Doc]) {
+	let (Some(first), Some(last)) = (docs.first(), docs.last()) else { return };
+	warning(first.scope.join(last.scope.clone()), ErrorInfo {
+		cause: "unused doc comment",
+		pointer: "this doc comment doesn't document a function, inline or scoreboard",
+		context: Some("doc comment"),
+		help: None,
+	}).warn();
+}
+
 #[derive(Debug)]
 pub struct Datapack {
 	pub name: Ident,
 	pub functions: Vec<Function>,
 	pub scoreboards: Vec<Scoreboard>,
 	pub inlines: Vec<Inline>,
+	pub includes: Vec<Include>,
+	pub macros: Vec<Macro>,
+	pub loop_limit: Option<LoopLimit>,
 }
 impl Datapack {
 	pub fn empty(name:Ident) -> Self {
@@ -315,20 +986,55 @@ impl Datapack {
 			functions: Vec::new(),
 			scoreboards: Vec::new(),
 			inlines: Vec::new(),
+			includes: Vec::new(),
+			macros: Vec::new(),
+			loop_limit: None,
 		}
 	}
 }
 
+#[derive(Debug, Clone)]
+pub struct Include {
+	pub keyword: Ident,
+	pub path: Str,
+	pub semi_colon: SrcScope,
+	// the name of the included datapack, once it's loaded
+	pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+	pub keyword: Ident,
+	pub name: Ident,
+	pub path: Str,
+	pub semi_colon: SrcScope,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoopLimit {
+	pub ident: Ident,
+	pub value: Int,
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
+	pub docs: Vec<Doc>,
+	pub module: String,
 	pub keyword: Ident,
 	pub name: Ident,
 	pub args: FunArgs,
 	pub block: Group,
+	pub body: Vec<Statement>,
 	pub at: Option<At>,
 }
+impl Function {
+	pub fn path(&self) -> String {
+		module_path(&self.module, &self.name.value)
+	}
+}
 #[derive(Debug, Clone)]
 pub struct Scoreboard {
+	pub docs: Vec<Doc>,
 	pub keyword: Ident,
 	pub objective: Ident,
 	pub name: Ident,
@@ -336,11 +1042,25 @@ pub struct Scoreboard {
 }
 #[derive(Debug, Clone)]
 pub struct Inline {
+	pub docs: Vec<Doc>,
+	pub module: String,
 	pub keyword: Ident,
 	pub name: Ident,
 	pub args: Vec<InlineArg>,
 	pub parens: SrcScope,
 	pub block: Group,
+	pub body: Vec<Statement>,
+}
+impl Inline {
+	pub fn path(&self) -> String {
+		module_path(&self.module, &self.name.value)
+	}
+}
+
+// the path of an item in a module, relative to the root of the datapack
+pub fn module_path(module:&str, name:&str) -> String {
+	if module.is_empty() { return name.to_owned() }
+	format!("{}/{}", module, name)
 }
 
 #[derive(Debug, Clone)]
@@ -363,10 +1083,16 @@ pub struct InlineArg {
 #[derive(Debug, Clone)]
 pub enum Statement {
 	Unsafe(Unsafe),
+	Scoreboard(Scoreboard),
 	Call(Call),
 	Pipe(Pipe),
-	If(If),
-	At(At),
+	Let(Let),
+	Assign(Assign),
+	If(Box<If>),
+	While(Box<While>),
+	For(Box<For>),
+	At(AtBlock),
+	Macro(MacroCall),
 }
 
 #[derive(Debug, Clone)] pub struct Unsafe {
@@ -375,38 +1101,243 @@ pub enum Statement {
 	pub commands: Vec<UnsafeCmd>
 }
 #[derive(Debug, Clone)] pub struct Call {
-	pub path: Path,
+	pub path: ResLoc,
+	pub group: Group,
+	pub args: Vec<Expr>,
+}
+impl Call {
+	// this call and the ones in its arguments
+	pub fn calls(&self) -> Vec<&Call> {
+		let mut calls = vec![self];
+		for arg in &self.args {
+			calls.extend(arg.calls());
+		}
+		calls
+	}
+}
+#[derive(Debug, Clone)] pub struct MacroCall {
+	pub name: Ident,
+	pub bang: Symbol,
 	pub group: Group,
-	pub args: Vec<Arg>,
+}
+impl MacroCall {
+	// the input of the macro, the value of a single string or else the source between the parentheses
+	pub fn input(&self) -> String {
+		if let [Token::Str(string), Token::End(_)] = self.group.tokens.as_slice() {
+			return string.value.clone();
+		}
+		let scope = self.group.scope.clone();
+		SrcScope { start: scope.start+1, end: scope.end-1, file: scope.file }.text().trim().to_owned()
+	}
 }
 #[derive(Debug, Clone)] pub struct Pipe {
 	pub symbol: Sep,
 	pub name: Ident,
 	pub equals: Symbol,
-	pub call: Call,
+	pub value: Expr,
 }
 #[derive(Debug, Clone)] pub struct If {
-	pub call: Call,
-	pub statements: Vec<Statement>,
+	pub ident: Ident,
+	pub condition: Expr,
+	pub block: Group,
+	pub body: Vec<Statement>,
+	pub otherwise: Option<Else>,
+}
+#[derive(Debug, Clone)] pub struct Else {
+	pub ident: Ident,
+	pub body: Vec<Statement>,
+}
+#[derive(Debug, Clone)] pub struct While {
+	pub ident: Ident,
+	pub condition: Expr,
+	pub block: Group,
+	pub body: Vec<Statement>,
+}
+#[derive(Debug, Clone)] pub struct For {
+	pub ident: Ident,
+	pub var: Ident,
+	pub start: Expr,
+	pub dots: Symbol,
+	pub end: Expr,
+	pub block: Group,
+	pub body: Vec<Statement>,
+}
+#[derive(Debug, Clone)] pub struct Let {
+	pub ident: Ident,
+	pub name: Ident,
+	pub equals: Symbol,
+	pub value: Expr,
+}
+#[derive(Debug, Clone)] pub struct Assign {
+	pub target: Arg,
+	pub symbol: Symbol,
+	// the operator of an assignment like `+=`
+	pub op: Option<Operator>,
+	pub value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+	// an integer, a pipe or a score
+	Value(Arg),
+	Call(Call),
+	Paren(Group, Box<Expr>),
+	Neg(Symbol, Box<Expr>),
+	Binary(Box<Binary>),
+	Compare(Box<Compare>),
+	In(Box<InRange>),
+}
+impl Expr {
+	pub fn scope(&self) -> SrcScope {
+		match self {
+			Self::Value(arg) => arg.scope(),
+			Self::Call(call) => call.path.scope.join(call.group.scope.clone()),
+			Self::Paren(group, _) => group.scope.clone(),
+			Self::Neg(symbol, expr) => symbol.scope.join(expr.scope()),
+			Self::Binary(binary) => binary.lhs.scope().join(binary.rhs.scope()),
+			Self::Compare(compare) => compare.lhs.scope().join(compare.rhs.scope()),
+			Self::In(in_range) => in_range.lhs.scope().join(in_range.range.scope()),
+		}
+	}
+	// every call in this expression, in the order they run
+	pub fn calls(&self) -> Vec<&Call> {
+		match self {
+			Self::Value(_) => Vec::new(),
+			Self::Call(call) => call.calls(),
+			Self::Paren(_, expr) | Self::Neg(_, expr) => expr.calls(),
+			Self::Binary(binary) => {
+				let mut calls = binary.lhs.calls();
+				calls.extend(binary.rhs.calls());
+				calls
+			}
+			Self::Compare(compare) => {
+				let mut calls = compare.lhs.calls();
+				calls.extend(compare.rhs.calls());
+				calls
+			}
+			Self::In(in_range) => in_range.lhs.calls(),
+		}
+	}
+}
+#[derive(Debug, Clone)] pub struct Binary {
+	pub lhs: Expr,
+	pub op: Operator,
+	pub symbol: Symbol,
+	pub rhs: Expr,
+}
+#[derive(Debug, Clone)] pub struct Compare {
+	pub lhs: Expr,
+	pub cmp: Comparison,
+	pub symbol: Symbol,
+	pub rhs: Expr,
+}
+// `<expr> in <range>`
+#[derive(Debug, Clone)] pub struct InRange {
+	pub lhs: Expr,
+	pub ident: Ident,
+	pub range: Range,
+}
+#[derive(Debug, Clone)] pub struct Range {
+	pub min: Option<Int>,
+	pub dots: Symbol,
+	pub max: Option<Int>,
+}
+impl Range {
+	pub fn scope(&self) -> SrcScope {
+		let start = self.min.as_ref().map_or(self.dots.scope.clone(), |min| min.scope.clone());
+		start.join(self.max.as_ref().map_or(self.dots.scope.clone(), |max| max.scope.clone()))
+	}
+	// the range as it's written in commands
+	pub fn text(&self) -> String {
+		match (&self.min, &self.max) {
+			(Some(min), Some(max)) if min.value == max.value => min.value.to_string(),
+			(min, max) => format!(
+				"{}..{}",
+				min.as_ref().map_or(String::new(), |min| min.value.to_string()),
+				max.as_ref().map_or(String::new(), |max| max.value.to_string()),
+			),
+		}
+	}
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Rem,
+}
+impl Operator {
+	pub fn parse_assign(symbol:&str) -> Option<Self> {
+		match symbol {
+			"+=" => Some(Self::Add),
+			"-=" => Some(Self::Sub),
+			"*=" => Some(Self::Mul),
+			"/=" => Some(Self::Div),
+			"%=" => Some(Self::Rem),
+			_ => None,
+		}
+	}
+	// the operation of `scoreboard players operation`
+	pub fn operation(self) -> &'static str {
+		match self {
+			Self::Add => "+=",
+			Self::Sub => "-=",
+			Self::Mul => "*=",
+			Self::Div => "/=",
+			Self::Rem => "%=",
+		}
+	}
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+impl Comparison {
+	pub fn parse(symbol:&str) -> Option<Self> {
+		match symbol {
+			"==" => Some(Self::Eq),
+			"!=" => Some(Self::Ne),
+			"<" => Some(Self::Lt),
+			"<=" => Some(Self::Le),
+			">" => Some(Self::Gt),
+			">=" => Some(Self::Ge),
+			_ => None,
+		}
+	}
+	// the comparison with its sides swapped
+	pub fn flip(self) -> Self {
+		match self {
+			Self::Lt => Self::Gt,
+			Self::Le => Self::Ge,
+			Self::Gt => Self::Lt,
+			Self::Ge => Self::Le,
+			cmp => cmp,
+		}
+	}
 }
 #[derive(Debug, Clone)] pub struct At {
 	pub ident: Ident,
 	pub loc: AtLoc,
 	pub loc_scope: SrcScope,
 }
+#[derive(Debug, Clone)] pub struct AtBlock {
+	pub ident: Ident,
+	pub target: Arg,
+	pub block: Group,
+	pub body: Vec<Statement>,
+}
 
-//
 #[derive(Debug, Clone)] pub struct UnsafeCmd {
-	pub tokens: Vec<Token>,
+	pub raw: Raw,
 	pub sep: Option<Sep>,
 }
-impl UnsafeCmd {
-	pub fn new() -> Self { UnsafeCmd {
-		tokens: Vec::new(),
-		sep: None,
-	}}
-}
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Arg {
 	pub tokens: Vec<Token>,
 	pub sep: Option<Sep>,
@@ -416,26 +1347,112 @@ impl Arg {
 		tokens: Vec::new(),
 		sep: None,
 	}}
+	pub fn scope(&self) -> SrcScope {
+		let first = self.tokens[0].scope();
+		first.join(self.tokens[self.tokens.len()-1].scope())
+	}
 }
+// `namespace:path/to/something`, where the namespace can be left out
 #[derive(Debug, Clone)]
-pub struct Path {
-	parts: Vec<Ident>,
-	seps: Vec<Sep>,
+pub struct ResLoc {
+	pub namespace: Option<String>,
+	pub path: String,
+	pub scope: SrcScope,
 }
-impl Path {
-	pub fn new(part:Ident) -> Self {
-		let mut path = Path { parts: Vec::new(), seps: Vec::new() };
-		path.parts.push(part);
-		path
+impl ResLoc {
+	// expects the tokens to be written without whitespace between them
+	pub fn new(tokens:&[Token], context:&str) -> Self {
+		Self::parse(tokens, context).fatal()
 	}
-	pub fn push_part(&mut self, sep:Sep, part:Ident) {
-		self.seps.push(sep);
-		self.parts.push(part);
+	pub fn parse(tokens:&[Token], context:&str) -> CompileResult<Self> {
+		let scope = tokens[0].scope().join(tokens[tokens.len()-1].scope());
+		let text = scope.text();
+		let (namespace, path) = match text.split_once(':') {
+			Some((namespace, path)) => (Some(namespace.to_owned()), path.to_owned()),
+			None => (None, text.clone()),
+		};
+		let loc = ResLoc { namespace, path, scope: scope.clone() };
+		// errors point at the character that's wrong
+		let path_start = text.chars().count() - loc.path.chars().count();
+		let at = |offset:usize| SrcScope { file: scope.file, start: scope.start+offset, end: scope.start+offset+1 };
+		if let Some(colon) = loc.path.chars().position(|c| c == ':') {
+			return CompileResult::Err(error(at(path_start+colon), ErrorInfo {
+				cause: "invalid resource location",
+				pointer: "a resource location only has one namespace",
+				context: Some(context),
+				help: None,
+			}));
+		}
+		let segments = loc.path.split('/').scan(path_start, |offset, segment| {
+			let start = *offset;
+			*offset += segment.chars().count() + 1;
+			Some((start, segment))
+		});
+		for (start, segment) in segments {
+			if segment.is_empty() {
+				return CompileResult::Err(error(at(start.max(1)-1), ErrorInfo {
+					cause: "invalid resource location",
+					pointer: "expected a name after this",
+					context: Some(context),
+					help: None,
+				}));
+			}
+		}
+		CompileResult::Ok(loc)
+	}
+
+	// whether `next` is written right after the tokens of a resource location and continues it.
+	// identifiers can't contain `-` or `.`, so before a namespace they're a minus and a dot,
+	// and only `/` separates the modules of a path
+	pub fn continues(tokens:&[Token], next:&Token) -> bool {
+		let Some(prev) = tokens.last() else { return false };
+		if prev.scope().end != next.scope().start { return false }
+		let namespaced = tokens.iter().any(|token| token.as_sep_w(':').is_some());
+		next.is_ident() || next.is_number() || next.as_sep_w(':').is_some()
+			|| next.as_symbol_w("/").is_some()
+			|| (namespaced && (next.as_symbol_w(".").is_some() || next.as_symbol_w("-").is_some()))
+	}
+	// whether the tokens are one resource location with a namespace or a path of multiple segments
+	pub fn glued(tokens:&[Token]) -> bool {
+		tokens.len() > 1 && tokens[0].is_ident()
+			&& (1..tokens.len()).all(|i| Self::continues(&tokens[..i], &tokens[i]))
+	}
+
+	// resource locations can only have lowercase letters, digits, `_`, `-`, `.` and `/` in their path
+	pub fn check_chars(&self, context:&str) -> CompileResult<()> {
+		let text = self.scope.text();
+		let path_start = text.chars().count() - self.path.chars().count();
+		for (i, c) in text.chars().enumerate() {
+			let valid = is_resource_char(c)
+				|| (c == '/' && i >= path_start) || (c == ':' && i+1 == path_start);
+			if valid { continue }
+			let start = self.scope.start + i;
+			return CompileResult::Err(error(SrcScope { file: self.scope.file.clone(), start, end: start+1 }, ErrorInfo {
+				cause: "invalid resource location",
+				pointer: "only lowercase letters, digits, `_`, `-`, `.` and `/` are allowed in resource locations",
+				context: Some(context),
+				help: None,
+			}));
+		}
+		CompileResult::Ok(())
+	}
+}
+// the characters Minecraft allows in namespaces and in the names that make up a path
+pub fn is_resource_char(c:char) -> bool {
+	c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-' || c == '.'
+}
+
+impl std::fmt::Display for ResLoc {
+	fn fmt(&self, f:&mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.namespace {
+			Some(namespace) => write!(f, "{}:{}", namespace, self.path),
+			None => f.write_str(self.path.as_str()),
+		}
 	}
 }
 
 // Enums
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AtLoc {
 	Player,
 	Players,
@@ -453,13 +1470,20 @@ impl AtLoc {
 			"entity" => CompileResult::Ok(Self::Entity),
 			"entities" => CompileResult::Ok(Self::Entities),
 			"any" => CompileResult::Ok(Self::Any),
-			_ => CompileResult::Err(error(ident.scope, ErrorInfo {
+			_ => CompileResult::Err(error(ident.scope.clone(), ErrorInfo {
 				cause: "invalid `location`",
 				pointer: "expected one of `player`, `players`, `entity`, `entities`, or `any`",
 				context, help
 			})),
 		}
 	}
+	pub fn describe(&self) -> &'static str {
+		match self {
+			Self::Player | Self::Players => "a player",
+			Self::Entity | Self::Entities => "an entity",
+			Self::Any => "anything",
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -468,6 +1492,8 @@ pub enum InlineArgType {
 	Objective,
 	Score,
 	Int,
+	// a resource location, like `minecraft:stone`
+	Resource,
 }
 impl InlineArgType {
 	pub fn parse(
@@ -478,11 +1504,111 @@ impl InlineArgType {
 			"objective" => CompileResult::Ok(Self::Objective),
 			"score" => CompileResult::Ok(Self::Score),
 			"int" => CompileResult::Ok(Self::Int),
-			_ => CompileResult::Err(error(ident.scope, ErrorInfo {
-				cause: "invalid `location`",
-				pointer: "expected one of `target`, `objective`, `score`, or `int`",
+			"resource" => CompileResult::Ok(Self::Resource),
+			_ => CompileResult::Err(error(ident.scope.clone(), ErrorInfo {
+				cause: "invalid inline argument type",
+				pointer: "expected one of `target`, `objective`, `score`, `int`, or `resource`",
 				context, help
 			})),
 		}
 	}
+	pub fn describe(&self) -> &'static str {
+		match self {
+			Self::Target => "a target",
+			Self::Objective => "an objective",
+			Self::Score => "a score",
+			Self::Int => "an int",
+			Self::Resource => "a resource location",
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::lexer::files::SourceMap;
+	use crate::debug::errors::fatal_scope;
+	use crate::lexer::Lexer;
+
+	fn parse_body(body:&str) -> Vec<Statement> {
+		let mut sources = SourceMap::new();
+		let file = sources.add("test.mccs", format!("datapack test;\nfunction f() {{ {} }}", body).as_str());
+		let tokens = Lexer::new(file).lex();
+		let datapack = Parser::new(tokens).parse();
+		datapack.functions[0].body.clone()
+	}
+
+	fn parse_source(source:&str) -> Datapack {
+		let mut sources = SourceMap::new();
+		let file = sources.add("test.mccs", source);
+		Parser::new(Lexer::new(file).lex()).parse()
+	}
+
+	fn let_value(body:&str) -> Expr {
+		match parse_body(body).remove(0) {
+			Statement::Let(local) => local.value,
+			statement => panic!("expected a let, got {:?}", statement),
+		}
+	}
+
+	#[test]
+	fn minus_before_call_is_subtraction() {
+		let Expr::Binary(binary) = let_value("let z = y-f();") else { panic!("expected a subtraction") };
+		assert_eq!(binary.op, Operator::Sub);
+		let Expr::Call(call) = &binary.rhs else { panic!("expected a call") };
+		assert_eq!(call.path.to_string(), "f");
+	}
+
+	#[test]
+	fn minus_between_names_is_subtraction() {
+		let Expr::Binary(binary) = let_value("let b = a-add(1, 2);") else { panic!("expected a subtraction") };
+		let Expr::Call(call) = &binary.rhs else { panic!("expected a call") };
+		assert_eq!(call.path.to_string(), "add");
+	}
+
+	#[test]
+	fn call_args_are_expressions() {
+		let Expr::Call(call) = let_value("let a = fact(n - 1, g(2));") else { panic!("expected a call") };
+		assert_eq!(call.args.len(), 2);
+		let Expr::Binary(binary) = &call.args[0] else { panic!("expected a subtraction") };
+		assert_eq!(binary.op, Operator::Sub);
+		let Expr::Call(inner) = &call.args[1] else { panic!("expected a call") };
+		assert_eq!(inner.path.to_string(), "g");
+		assert_eq!(call.calls().len(), 2);
+	}
+
+	#[test]
+	fn namespaced_call_args_are_one_value() {
+		let Expr::Call(call) = let_value("let a = place(minecraft:oak-log, x-1);") else { panic!("expected a call") };
+		let Expr::Value(arg) = &call.args[0] else { panic!("expected a value") };
+		assert_eq!(arg.tokens.len(), 5);
+		assert!(matches!(&call.args[1], Expr::Binary(_)));
+	}
+
+	#[test]
+	fn module_and_namespaced_paths_are_glued() {
+		let Expr::Call(call) = let_value("let a = util/helper();") else { panic!("expected a call") };
+		assert_eq!(call.path.to_string(), "util/helper");
+		let Expr::Call(call) = let_value("let a = pack:my-mod/f.x();") else { panic!("expected a call") };
+		assert_eq!(call.path.namespace.as_deref(), Some("pack"));
+		assert_eq!(call.path.path, "my-mod/f.x");
+	}
+
+	#[test]
+	fn inline_types_take_no_parameters() {
+		// the error points at the `<`, whatever follows it
+		assert_eq!(fatal_scope(|| parse_source("datapack test;\ninline f(x: int<>) {}")), (30, 31));
+		assert_eq!(fatal_scope(|| parse_source("datapack test;\ninline f(x: int<a<b>>) {}")), (30, 31));
+		assert_eq!(fatal_scope(|| parse_source("datapack test;\ninline f(x: int<) {}")), (30, 31));
+	}
+
+	#[test]
+	fn range_bounds_are_integers() {
+		// the bodies start at 30
+		assert_eq!(fatal_scope(|| parse_body("let x = 2; if x in n..5 {}")), (49, 50));
+		assert_eq!(fatal_scope(|| parse_body("let x = 2; if x in 1..(4) {}")), (52, 55));
+		assert_eq!(fatal_scope(|| parse_body("let x = 2; if x in 1..2+3 {}")), (53, 54));
+		assert_eq!(fatal_scope(|| parse_body("let x = 2; if x in 5..1 {}")), (49, 53));
+		parse_body("let x = 2; if x in -1..5 {} if x in 3.. {} while x in ..4 { x += 1; }");
+	}
 }
@@ -0,0 +1,174 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::compiler::OUT_DIR;
+use crate::debug::errors::{error, ErrorInfo};
+use crate::lexer::files::{FileRef, SourceMap};
+use crate::lexer::Lexer;
+use crate::lexer::tokens::SrcScope;
+use crate::parser::{is_resource_char, Datapack, Parser};
+
+pub const ROOT_FILE:&str = "datapack.mccs";
+pub const EXTENSION:&str = "mccs";
+
+// loads the project in `dir` and every datapack it includes,
+// the included datapacks come before the ones including them, so the project is the last one
+pub fn load(sources:&mut SourceMap, dir:&Path) -> Vec<Datapack> {
+	let mut loader = Loader { sources, loaded: Vec::new(), chain: Vec::new(), packs: Vec::new() };
+	loader.load_pack(dir, None);
+	loader.packs
+}
+
+struct Loader<'s> {
+	sources: &'s mut SourceMap,
+	// the directory of every datapack that has been loaded, by the index of the datapack
+	loaded: Vec<PathBuf>,
+	// the datapacks being loaded, each with its directory and the include that led to it
+	chain: Vec<(PathBuf, String, Option<SrcScope>)>,
+	packs: Vec<Datapack>,
+}
+impl Loader<'_> {
+	// loads the datapack in `dir` and the ones it includes, and returns its name
+	fn load_pack(&mut self, dir:&Path, include:Option<SrcScope>) -> String {
+		let Ok(canonical) = fs::canonicalize(dir) else {
+			match include {
+				Some(scope) => error(scope, ErrorInfo {
+					cause: "unknown datapack",
+					pointer: "no directory exists at this path",
+					context: Some("include"),
+					help: None,
+				}).fatal(),
+				None => panic!("failed to open project directory {}", dir.display()),
+			}
+		};
+		if let Some(start) = self.chain.iter().position(|(dir, _, _)| *dir == canonical) {
+			self.include_cycle(start, include.unwrap());
+		}
+		if let Some(index) = self.loaded.iter().position(|dir| *dir == canonical) {
+			return self.packs[index].name.value.clone();
+		}
+
+		let mut datapack = load_project(self.sources, dir);
+		self.chain.push((canonical.clone(), datapack.name.value.clone(), include.clone()));
+		for include in &mut datapack.includes {
+			let path = dir.join(&include.path.value);
+			include.namespace = Some(self.load_pack(&path, Some(include.path.scope.clone())));
+		}
+		self.chain.pop();
+
+		let name = datapack.name.value.clone();
+		if let Some(existing) = self.packs.iter().find(|pack| pack.name.value == name) {
+			let err = error(datapack.name.scope, ErrorInfo {
+				cause: "duplicate datapack",
+				pointer: "another datapack with this name is part of the compilation",
+				context: Some("datapack declaration"),
+				help: None,
+			}).note(existing.name.scope.clone(), "declared here");
+			match include {
+				Some(scope) => err.note(scope, "included here").fatal(),
+				None => err.fatal(),
+			}
+		}
+		self.loaded.push(canonical);
+		self.packs.push(datapack);
+		name
+	}
+
+	// `include` leads back to the datapack at `start` in the chain
+	fn include_cycle(&self, start:usize, include:SrcScope) -> ! {
+		let (_, last, _) = self.chain.last().unwrap();
+		let mut err = error(include, ErrorInfo {
+			cause: "include cycle",
+			pointer: format!(
+				"`{}` includes `{}`, which is already being included",
+				last, self.chain[start].1
+			).as_str(),
+			context: Some("include"),
+			help: None,
+		});
+		for i in start+1..self.chain.len() {
+			err = err.note(self.chain[i].2.clone().unwrap(), format!(
+				"`{}` includes `{}` here", self.chain[i-1].1, self.chain[i].1
+			).as_str());
+		}
+		err.fatal()
+	}
+}
+
+// loads every file of the project in `dir` into one datapack.
+// `datapack.mccs` declares the datapack, every other file is a module at its path from `dir`,
+// so the items in `some_mod/inner_mod.mccs` are in the module `some_mod/inner_mod`
+fn load_project(sources:&mut SourceMap, dir:&Path) -> Datapack {
+	let root = dir.join(ROOT_FILE);
+	if !root.is_file() {
+		panic!("no {} in project directory {}", ROOT_FILE, dir.display());
+	}
+	let file = sources.load(path_str(&root).as_str());
+	let tokens = Lexer::new(file).lex();
+	let mut datapack = Parser::new(tokens).parse();
+
+	let mut files = Vec::new();
+	find_files(dir, &dir.join(OUT_DIR), &mut files);
+	for path in files {
+		if path == root { continue }
+		let file = sources.load(path_str(&path).as_str());
+		let module = module_name(dir, &path, &file);
+		let tokens = Lexer::new(file).lex();
+		Parser::for_module(tokens, module.as_str()).parse_module(&mut datapack);
+	}
+	datapack
+}
+
+// every source file in `dir` and its subdirectories, in a stable order.
+// a directory with its own `datapack.mccs` is another datapack, which can only be included
+fn find_files(dir:&Path, out:&Path, files:&mut Vec<PathBuf>) {
+	let entries = fs::read_dir(dir).unwrap_or_else(|_| panic!(
+		"failed to read directory {}", dir.display()
+	));
+	let mut paths:Vec<PathBuf> = entries
+		.map(|entry| entry.unwrap_or_else(|_| panic!(
+			"failed to read directory {}", dir.display()
+		)).path())
+		.collect();
+	paths.sort();
+	for path in paths {
+		if path.is_dir() {
+			// hidden directories and the output are never part of the project
+			let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+			if hidden || path == out || path.join(ROOT_FILE).is_file() { continue }
+			find_files(&path, out, files);
+		} else if path.extension().is_some_and(|ext| ext == EXTENSION) {
+			files.push(path);
+		}
+	}
+}
+
+// the module of a file is its path from the project directory, without the extension.
+// it becomes the directory of its functions, so it must only use the characters Minecraft allows there
+fn module_name(dir:&Path, path:&Path, file:&FileRef) -> String {
+	let relative = path.strip_prefix(dir).unwrap().with_extension("");
+	let segments:Vec<String> = relative.components()
+		.map(|segment| segment.as_os_str().to_string_lossy().into_owned())
+		.collect();
+	// an empty file doesn't add anything to the module
+	if file.source().is_empty() { return segments.join("/") }
+	for segment in &segments {
+		if !segment.chars().all(is_resource_char) {
+			// there's no source for the name, so the error points at the start of the file
+			error(SrcScope { file: file.clone(), start: 0, end: 1 }, ErrorInfo {
+				cause: "invalid module name",
+				pointer: format!(
+					"the module `{}` of this file can only have lowercase letters, digits, `_`, `-` and `.`",
+					segment
+				).as_str(),
+				context: Some("module"),
+				help: None,
+			}).fatal();
+		}
+	}
+	segments.join("/")
+}
+
+// paths in the current directory are shown without the leading `./`
+fn path_str(path:&Path) -> String {
+	path.strip_prefix(".").unwrap_or(path).to_string_lossy().into_owned()
+}